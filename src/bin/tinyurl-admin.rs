@@ -0,0 +1,924 @@
+//! Administrative CLI for link management and maintenance, sharing the
+//! configuration, repository and service layer of the HTTP server.
+
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand};
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+use tinyurl_rs::core::background::BackgroundTasks;
+use tinyurl_rs::core::config::Config;
+use tinyurl_rs::core::db_connect::DatabaseManager;
+use tinyurl_rs::core::public_url::PublicUrl;
+use tinyurl_rs::models::{
+    normalize_host, AppError, ConflictPolicy, CreateUrlRequest, ImportOptions, ImportReport,
+    LinkListQuery, LinkVariant, RedirectRule, Result, TimeseriesInterval, TinyUrl, TransferFormat, UpdateUrlRequest,
+    UtmParams, template_placeholders, DEFAULT_DISABLED_REASON,
+};
+use tinyurl_rs::repository::PostgresUrlRepository;
+use tinyurl_rs::services::{DefaultUrlService, HttpPageFetcher, RedisCacheService, ShortCodeStrategy};
+use tinyurl_rs::traits::UrlService;
+
+type Service = DefaultUrlService<PostgresUrlRepository, RedisCacheService, ShortCodeStrategy>;
+
+/// One JSON argument rather than a repeated flag, which clap assumes for `Vec`
+type RedirectRules = Vec<RedirectRule>;
+type Variants = Vec<LinkVariant>;
+
+/// Manage TinyURL links and run maintenance tasks
+#[derive(Parser)]
+#[command(name = "tinyurl-admin", version)]
+struct Cli {
+    /// Config file (TOML or YAML); environment variables still override it
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Print results as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// A link addressed by code and optional custom domain
+#[derive(Args)]
+struct LinkArgs {
+    /// Short code
+    code: String,
+
+    /// Custom domain of the link, the default domain when omitted
+    #[arg(long)]
+    domain: Option<String>,
+}
+
+/// Descriptive fields of a link; on update, an empty value removes the field
+#[derive(Args)]
+struct MetadataArgs {
+    /// Human-readable name
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Longer explanation of what the link is for
+    #[arg(long)]
+    description: Option<String>,
+
+    /// Label for grouping and filtering; repeat for several
+    #[arg(long)]
+    tag: Vec<String>,
+
+    /// Internal notes
+    #[arg(long)]
+    notes: Option<String>,
+}
+
+/// Activation window of a link and where visitors go outside it
+#[derive(Args)]
+struct ScheduleArgs {
+    /// Time the link goes live (RFC 3339)
+    #[arg(long)]
+    active_from: Option<DateTime<Utc>>,
+
+    /// Time the link stops going to its URL (RFC 3339)
+    #[arg(long)]
+    active_until: Option<DateTime<Utc>>,
+
+    /// Destination outside the activation window; on update, '' removes it
+    #[arg(long, value_name = "URL")]
+    fallback_url: Option<String>,
+}
+
+/// Campaign parameters added to the destination; on update, an empty value removes one
+#[derive(Args)]
+struct UtmArgs {
+    /// utm_source, e.g. newsletter
+    #[arg(long)]
+    utm_source: Option<String>,
+
+    /// utm_medium, e.g. email
+    #[arg(long)]
+    utm_medium: Option<String>,
+
+    /// utm_campaign
+    #[arg(long)]
+    utm_campaign: Option<String>,
+
+    /// utm_term
+    #[arg(long)]
+    utm_term: Option<String>,
+
+    /// utm_content
+    #[arg(long)]
+    utm_content: Option<String>,
+}
+
+impl UtmArgs {
+    fn into_params(self) -> UtmParams {
+        UtmParams {
+            source: self.utm_source,
+            medium: self.utm_medium,
+            campaign: self.utm_campaign,
+            term: self.utm_term,
+            content: self.utm_content,
+        }
+    }
+}
+
+impl LinkArgs {
+    fn domain_key(&self) -> String {
+        self.domain.as_deref().map(normalize_host).unwrap_or_default()
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create or upgrade the database schema
+    Migrate,
+
+    /// Shorten a URL
+    Create {
+        /// Destination URL
+        url: String,
+
+        /// Custom short code
+        #[arg(long)]
+        code: Option<String>,
+
+        /// Registered custom domain for the link
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Expiry time (RFC 3339, e.g. 2025-01-31T00:00:00Z)
+        #[arg(long)]
+        expires_at: Option<DateTime<Utc>>,
+
+        #[command(flatten)]
+        schedule: ScheduleArgs,
+
+        /// Always show the preview page instead of redirecting straight away
+        #[arg(long)]
+        interstitial: bool,
+
+        /// Per-platform destinations as a JSON array, e.g.
+        /// '[{"os":["ios"],"url":"https://apps.apple.com/app/id1"}]'
+        #[arg(long, value_name = "JSON", value_parser = parse_redirect_rules)]
+        redirect_rules: Option<RedirectRules>,
+
+        /// Weighted destinations splitting visitors as a JSON array, e.g.
+        /// '[{"url":"https://example.com/a","weight":70},{"url":"https://example.com/b","weight":30}]'
+        #[arg(long, value_name = "JSON", value_parser = parse_variants)]
+        variants: Option<Variants>,
+
+        /// Send every visitor to this variant
+        #[arg(long, value_name = "NAME")]
+        lock_variant: Option<String>,
+
+        #[command(flatten)]
+        utm: UtmArgs,
+
+        /// Forward the short URL's query string and any path after the code
+        #[arg(long)]
+        passthrough: bool,
+
+        /// Treat the URL as a template, e.g. 'https://github.com/org/{repo}',
+        /// filled from path segments after the code or query parameters
+        #[arg(long)]
+        template: bool,
+
+        #[command(flatten)]
+        metadata: MetadataArgs,
+    },
+
+    /// Show a link with all its fields
+    Show(LinkArgs),
+
+    /// Change a link's destination, expiry or metadata
+    Update {
+        #[command(flatten)]
+        link: LinkArgs,
+
+        /// New destination URL
+        #[arg(long)]
+        url: Option<String>,
+
+        /// New expiry time (RFC 3339)
+        #[arg(long, conflicts_with = "no_expiry")]
+        expires_at: Option<DateTime<Utc>>,
+
+        /// Remove the expiry time
+        #[arg(long)]
+        no_expiry: bool,
+
+        #[command(flatten)]
+        schedule: ScheduleArgs,
+
+        /// Remove the start of the activation window
+        #[arg(long, conflicts_with = "active_from")]
+        no_active_from: bool,
+
+        /// Remove the end of the activation window
+        #[arg(long, conflicts_with = "active_until")]
+        no_active_until: bool,
+
+        /// Turn the always-preview mode on or off
+        #[arg(long, value_name = "BOOL")]
+        interstitial: Option<bool>,
+
+        /// Replacement per-platform destinations as a JSON array, '[]' to remove them
+        #[arg(long, value_name = "JSON", value_parser = parse_redirect_rules)]
+        redirect_rules: Option<RedirectRules>,
+
+        /// Replacement weighted destinations as a JSON array, '[]' to remove them
+        #[arg(long, value_name = "JSON", value_parser = parse_variants)]
+        variants: Option<Variants>,
+
+        /// Send every visitor to this variant, '' to resume the split
+        #[arg(long, value_name = "NAME")]
+        lock_variant: Option<String>,
+
+        #[command(flatten)]
+        utm: UtmArgs,
+
+        /// Turn query and path forwarding on or off
+        #[arg(long, value_name = "BOOL")]
+        passthrough: Option<bool>,
+
+        /// Turn template expansion of the URL on or off
+        #[arg(long, value_name = "BOOL")]
+        template: Option<bool>,
+
+        #[command(flatten)]
+        metadata: MetadataArgs,
+
+        /// Remove all tags; `--tag` replaces them instead
+        #[arg(long, conflicts_with = "tag")]
+        clear_tags: bool,
+    },
+
+    /// List links, newest first
+    List {
+        /// Only links with this tag; repeat to require several
+        #[arg(long)]
+        tag: Vec<String>,
+
+        /// Text searched in code, title and destination
+        #[arg(long)]
+        search: Option<String>,
+
+        /// Only links on this domain, `""` for the default one
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Include deleted links
+        #[arg(long)]
+        include_deleted: bool,
+
+        /// Number of links to show
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+
+        /// Continue below this link ID, as printed by the previous page
+        #[arg(long)]
+        before: Option<i32>,
+    },
+
+    /// Stop a link from redirecting
+    Disable {
+        #[command(flatten)]
+        link: LinkArgs,
+
+        /// Reason recorded on the link
+        #[arg(long, default_value = DEFAULT_DISABLED_REASON)]
+        reason: String,
+    },
+
+    /// Re-enable a disabled link
+    Enable(LinkArgs),
+
+    /// Delete a link, keeping its code reserved for the quarantine period
+    Delete(LinkArgs),
+
+    /// Restore a deleted link whose code is still reserved
+    Restore(LinkArgs),
+
+    /// Delete links whose expiry time has passed, keeping their codes reserved
+    PurgeExpired {
+        /// Purge links expired at or before this time instead of now (RFC 3339)
+        #[arg(long)]
+        before: Option<DateTime<Utc>>,
+
+        /// Only count the links that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Roll raw clicks up into hourly and daily counts, pruning both after
+    /// their retention periods
+    RollUpClicks,
+
+    /// Rewrite cached destinations in Redis from the database
+    RebuildCache,
+
+    /// Bulk import links from a CSV or JSONL file
+    Import {
+        /// Input file, `-` for standard input
+        file: PathBuf,
+
+        /// `csv` or `jsonl`; guessed from the file extension when omitted
+        #[arg(long)]
+        format: Option<TransferFormat>,
+
+        /// What to do with codes that already exist: `skip`, `overwrite` or `fail`
+        #[arg(long, default_value = "fail")]
+        on_conflict: ConflictPolicy,
+
+        /// Validate and report without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Export all links as CSV or JSONL
+    Export {
+        /// `csv` or `jsonl`; guessed from the output extension when omitted
+        #[arg(long)]
+        format: Option<TransferFormat>,
+
+        /// Output file instead of standard output
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Show link totals, or one link's statistics when a code is given
+    Stats {
+        /// Short code
+        code: Option<String>,
+
+        /// Custom domain of the link
+        #[arg(long)]
+        domain: Option<String>,
+    },
+
+    /// Show a link's clicks over time; `--json` adds referrer, country,
+    /// device and browser breakdowns
+    Timeseries {
+        #[command(flatten)]
+        link: LinkArgs,
+
+        /// `hour`, `day` or `week`
+        #[arg(long, default_value = "day")]
+        interval: TimeseriesInterval,
+
+        /// Start of the range (RFC 3339); 24 hours, 30 days or 12 weeks before its end by default
+        #[arg(long)]
+        from: Option<DateTime<Utc>>,
+
+        /// End of the range (RFC 3339), now by default
+        #[arg(long)]
+        to: Option<DateTime<Utc>>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // Logs go to stderr so stdout stays parseable
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")))
+        .init();
+
+    // `Config::load` reads `--config` from the arguments itself
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(cli, &config).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli, config: &Config) -> Result<()> {
+    let db = Arc::new(DatabaseManager::new(&config.database).await?);
+    let cache = Arc::new(RedisCacheService::new(
+        config.cache.redis_url.clone(),
+        config.cache.ttl_secs,
+    )?);
+    let background = BackgroundTasks::new(tokio::runtime::Handle::current());
+    let mut service = DefaultUrlService::new(
+        Arc::new(PostgresUrlRepository::new(db.get_pool())),
+        Arc::clone(&cache),
+        Arc::new(config.short_code_strategy()?),
+        config.generator.length,
+        config.cache.ttl_secs,
+        chrono::Duration::days(config.generator.quarantine_days.into()),
+        background.clone(),
+    )
+    .with_hourly_click_retention(chrono::Duration::days(config.analytics.hourly_retention_days.into()));
+    if config.page_fetch.enabled {
+        service = service.with_page_fetcher(Arc::new(HttpPageFetcher::new(&config.page_fetch)?));
+    }
+
+    let result = execute(cli.command, cli.json, config, &db, &cache, &service).await;
+    // Page metadata of created and updated links is fetched in the background
+    background
+        .drain(Duration::from_secs(config.server.drain_timeout_secs))
+        .await;
+    db.close().await;
+    result
+}
+
+async fn execute(
+    command: Command,
+    json: bool,
+    config: &Config,
+    db: &DatabaseManager,
+    cache: &RedisCacheService,
+    service: &Service,
+) -> Result<()> {
+    match command {
+        Command::Migrate => {
+            db.migrate().await?;
+            print(json, &serde_json::json!({ "migrated": true }), || {
+                "Migrations applied".to_string()
+            })
+        }
+
+        Command::Create {
+            url,
+            code,
+            domain,
+            expires_at,
+            schedule,
+            interstitial,
+            redirect_rules,
+            variants,
+            lock_variant,
+            utm,
+            passthrough,
+            template,
+            metadata,
+        } => {
+            let request = CreateUrlRequest {
+                url,
+                custom_code: code,
+                domain,
+                expires_at,
+                active_from: schedule.active_from,
+                active_until: schedule.active_until,
+                fallback_url: schedule.fallback_url,
+                title: metadata.title,
+                description: metadata.description,
+                tags: metadata.tag,
+                notes: metadata.notes,
+                interstitial,
+                redirect_rules: redirect_rules.unwrap_or_default(),
+                variants: variants.unwrap_or_default(),
+                locked_variant: lock_variant,
+                utm: utm.into_params(),
+                passthrough,
+                template,
+            };
+            let created = service.create_short_url(request, &default_base_url(config)?).await?;
+            print(json, &created, || created.short_url.clone())
+        }
+
+        Command::Show(link) => {
+            let url = service.get_url(&link.domain_key(), &link.code).await?;
+            print(json, &url, || describe(&url))
+        }
+
+        Command::Update {
+            link,
+            url,
+            expires_at,
+            no_expiry,
+            schedule,
+            no_active_from,
+            no_active_until,
+            interstitial,
+            redirect_rules,
+            variants,
+            lock_variant,
+            utm,
+            passthrough,
+            template,
+            metadata,
+            clear_tags,
+        } => {
+            let tags = if clear_tags {
+                Some(Vec::new())
+            } else {
+                Some(metadata.tag).filter(|tags| !tags.is_empty())
+            };
+            let request = UpdateUrlRequest {
+                url,
+                expires_at,
+                clear_expires_at: no_expiry,
+                active_from: schedule.active_from,
+                clear_active_from: no_active_from,
+                active_until: schedule.active_until,
+                clear_active_until: no_active_until,
+                fallback_url: schedule.fallback_url,
+                title: metadata.title,
+                description: metadata.description,
+                tags,
+                notes: metadata.notes,
+                interstitial,
+                redirect_rules,
+                variants,
+                locked_variant: lock_variant,
+                utm: Some(utm.into_params()).filter(|utm| *utm != UtmParams::default()),
+                passthrough,
+                template,
+            };
+            let url = service.update_url(&link.domain_key(), &link.code, request).await?;
+            print(json, &url, || describe(&url))
+        }
+
+        Command::List {
+            tag,
+            search,
+            domain,
+            include_deleted,
+            limit,
+            before,
+        } => {
+            let query = LinkListQuery {
+                domain,
+                tag: Some(tag.join(",")),
+                q: search,
+                include_deleted,
+                before,
+                limit: Some(limit),
+            };
+            let page = service.list_links(query).await?;
+            print(json, &page, || {
+                let mut lines: Vec<String> = page
+                    .links
+                    .iter()
+                    .map(|url| {
+                        let code = if url.domain.is_empty() {
+                            url.short_code.clone()
+                        } else {
+                            format!("{}/{}", url.domain, url.short_code)
+                        };
+                        format!(
+                            "{:>8}  {:<24}  {:<30}  {}{}",
+                            url.id,
+                            code,
+                            url.title.as_deref().unwrap_or("-"),
+                            url.long_url,
+                            if url.tags.is_empty() {
+                                String::new()
+                            } else {
+                                format!("  [{}]", url.tags.join(", "))
+                            }
+                        )
+                    })
+                    .collect();
+                if let Some(next_before) = page.next_before {
+                    lines.push(format!("more: --before {}", next_before));
+                }
+                lines.join("\n")
+            })
+        }
+
+        Command::Disable { link, reason } => {
+            let url = service
+                .set_disabled(&link.domain_key(), &link.code, Some(reason))
+                .await?;
+            print(json, &url, || describe(&url))
+        }
+
+        Command::Enable(link) => {
+            let url = service.set_disabled(&link.domain_key(), &link.code, None).await?;
+            print(json, &url, || describe(&url))
+        }
+
+        Command::Delete(link) => {
+            if !service.delete_url(&link.domain_key(), &link.code).await? {
+                return Err(AppError::NotFound(format!("Short code '{}' not found", link.code)));
+            }
+            print(json, &serde_json::json!({ "deleted": link.code }), || {
+                format!("Deleted {}", link.code)
+            })
+        }
+
+        Command::Restore(link) => {
+            let url = service.restore_url(&link.domain_key(), &link.code).await?;
+            print(json, &url, || describe(&url))
+        }
+
+        Command::PurgeExpired { before, dry_run } => {
+            let before = before.unwrap_or_else(Utc::now);
+            let count = service.purge_expired(before, dry_run).await?;
+            let report = serde_json::json!({ "before": before, "dry_run": dry_run, "count": count });
+            print(json, &report, || {
+                if dry_run {
+                    format!("{} expired links would be purged", count)
+                } else {
+                    format!("Purged {} expired links", count)
+                }
+            })
+        }
+
+        Command::RollUpClicks => {
+            let (events_before, hours_before) = config.analytics.retention_cutoffs(Utc::now());
+            let report = service.roll_up_clicks(events_before, hours_before).await?;
+            print(json, &report, || {
+                format!(
+                    "Rolled up {} clicks, pruned {} raw clicks and {} hourly counts",
+                    report.rolled_up, report.pruned_events, report.pruned_hours
+                )
+            })
+        }
+
+        Command::RebuildCache => {
+            if !cache.has_redis() {
+                return Err(AppError::Validation(
+                    "REDIS_URL is not set, there is no shared cache to rebuild".to_string(),
+                ));
+            }
+            let summary = service.rebuild_cache().await?;
+            print(json, &summary, || {
+                format!(
+                    "Cached {} active links, evicted {} inactive ones",
+                    summary.cached, summary.evicted
+                )
+            })
+        }
+
+        Command::Import {
+            file,
+            format,
+            on_conflict,
+            dry_run,
+        } => {
+            let format = format
+                .or_else(|| TransferFormat::from_path(&file))
+                .unwrap_or_default();
+            let input: Box<dyn Read + Send> = if file.as_os_str() == "-" {
+                Box::new(io::stdin())
+            } else {
+                Box::new(File::open(&file).map_err(|e| {
+                    AppError::Validation(format!("Cannot open {}: {}", file.display(), e))
+                })?)
+            };
+
+            let options = ImportOptions {
+                format,
+                on_conflict,
+                dry_run,
+            };
+            let report = service.import_links(input, options).await?;
+            print(json, &report, || describe_import(&report))
+        }
+
+        Command::Export { format, output } => {
+            let format = format
+                .or_else(|| output.as_deref().and_then(TransferFormat::from_path))
+                .unwrap_or_default();
+            let mut out: Box<dyn Write> = match &output {
+                Some(path) => Box::new(File::create(path).map_err(|e| {
+                    AppError::Validation(format!("Cannot create {}: {}", path.display(), e))
+                })?),
+                None => Box::new(io::stdout().lock()),
+            };
+
+            let write_error = |e: io::Error| AppError::Internal(format!("Failed to write export: {}", e));
+            let mut stream = service.export_links(format).await?;
+            while let Some(chunk) = stream.next().await {
+                out.write_all(&chunk?).map_err(write_error)?;
+            }
+            out.flush().map_err(write_error)
+        }
+
+        Command::Stats { code: Some(code), domain } => {
+            let link = LinkArgs { code, domain };
+            let stats = service.get_url_stats(&link.domain_key(), &link.code).await?;
+            print(json, &stats, || {
+                let mut described = format!(
+                    "{} -> {}\nclicks:  {}\ncreated: {}\nupdated: {}",
+                    stats.short_code, stats.long_url, stats.clicks, stats.created_at, stats.updated_at
+                );
+                let mut countries: Vec<_> = stats.countries.iter().collect();
+                countries.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+                for (country, clicks) in countries {
+                    described.push_str(&format!("\n  {}:    {}", country, clicks));
+                }
+                described.push_str(&format!("\nunique:  {}", stats.unique_visitors));
+                for (day, visitors) in stats.daily_unique_visitors.iter().rev() {
+                    described.push_str(&format!("\n  {}: {}", day, visitors));
+                }
+                described.push_str(&format!("\nbots:    {}", stats.bot_clicks));
+                let mut bots: Vec<_> = stats.bots.iter().collect();
+                bots.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+                for (bot, clicks) in bots {
+                    described.push_str(&format!("\n  {}: {}", bot, clicks));
+                }
+                for variant in &stats.variants {
+                    described.push_str(&format!(
+                        "\nvariant {}: {} clicks, weight {}{} -> {}",
+                        variant.name,
+                        variant.clicks,
+                        variant.weight,
+                        if variant.locked { ", locked" } else { "" },
+                        variant.url
+                    ));
+                }
+                described
+            })
+        }
+
+        Command::Timeseries { link, interval, from, to } => {
+            let timeseries = service
+                .get_url_timeseries(&link.domain_key(), &link.code, interval, from, to)
+                .await?;
+            print(json, &timeseries, || {
+                let format = match interval {
+                    TimeseriesInterval::Hour => "%Y-%m-%d %H:00",
+                    TimeseriesInterval::Day | TimeseriesInterval::Week => "%Y-%m-%d",
+                };
+                let mut described = format!(
+                    "{} clicks per {} from {} to {}",
+                    timeseries.short_code, interval, timeseries.from, timeseries.to
+                );
+                for point in &timeseries.points {
+                    described.push_str(&format!("\n  {}: {}", point.start.format(format), point.clicks));
+                }
+                described
+            })
+        }
+
+        Command::Stats { code: None, .. } => {
+            let summary = service.link_summary().await?;
+            print(json, &summary, || {
+                format!(
+                    "links:    {} ({} active, {} disabled, {} expired, {} deleted)\nclicks:   {}\ndomains:  {}",
+                    summary.total_links,
+                    summary.active_links,
+                    summary.disabled_links,
+                    summary.expired_links,
+                    summary.deleted_links,
+                    summary.total_clicks,
+                    summary.domains
+                )
+            })
+        }
+    }
+}
+
+/// Base URL for printed short URLs: `PUBLIC_BASE_URL`, else the listen address
+fn default_base_url(config: &Config) -> Result<String> {
+    let public_url = PublicUrl::new(
+        config.server.public_base_url.as_deref(),
+        &config.security.trusted_proxies,
+    )?;
+
+    Ok(match public_url.configured_base_url() {
+        Some(base_url) => base_url.to_string(),
+        None => format!("http://{}:{}", config.server.host, config.server.port),
+    })
+}
+
+/// Print `value` as JSON, or the human-readable rendering
+fn print<T: Serialize>(json: bool, value: &T, human: impl FnOnce() -> String) -> Result<()> {
+    if json {
+        let rendered = serde_json::to_string_pretty(value)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize output: {}", e)))?;
+        println!("{}", rendered);
+    } else {
+        println!("{}", human());
+    }
+    Ok(())
+}
+
+fn describe_import(report: &ImportReport) -> String {
+    let mut lines = vec![format!(
+        "{}read {} rows: {} inserted, {} updated, {} skipped, {} invalid, {} duplicates, {} conflicts",
+        if report.dry_run { "[dry run] " } else { "" },
+        report.rows_read,
+        report.inserted,
+        report.updated,
+        report.skipped,
+        report.invalid,
+        report.duplicates,
+        report.conflicts
+    )];
+    if report.failed {
+        lines.push("the import would fail because of the conflicts below; nothing would be imported".to_string());
+    }
+    for issue in &report.issues {
+        lines.push(format!(
+            "  line {}{}: {}",
+            issue.line,
+            issue
+                .short_code
+                .as_deref()
+                .map(|code| format!(" ({})", code))
+                .unwrap_or_default(),
+            issue.message
+        ));
+    }
+    lines.join("\n")
+}
+
+/// UTM parameters as they appear in the destination's query string
+fn utm_query(utm: &UtmParams) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    query.extend_pairs(utm.pairs());
+    query.finish()
+}
+
+fn describe(url: &TinyUrl) -> String {
+    let domain = if url.domain.is_empty() { "(default)" } else { &url.domain };
+    let status = match (&url.deleted_at, &url.disabled_reason) {
+        (Some(deleted_at), _) => format!("deleted at {}", deleted_at.to_rfc3339()),
+        (None, Some(reason)) => format!("disabled ({})", reason),
+        (None, None) if url.is_expired(Utc::now()) => "expired".to_string(),
+        (None, None) if !url.is_live(Utc::now()) => "outside its activation window".to_string(),
+        (None, None) => "active".to_string(),
+    };
+    let expires = url
+        .expires_at
+        .map(|expires_at| expires_at.to_rfc3339())
+        .unwrap_or_else(|| "never".to_string());
+
+    let mut described = format!(
+        "code:     {}\ndomain:   {}\nurl:      {}\nstatus:   {}\nclicks:   {}\ncreated:  {}\nupdated:  {}\nexpires:  {}",
+        url.short_code,
+        domain,
+        url.long_url,
+        status,
+        url.clicks,
+        url.created_at.to_rfc3339(),
+        url.updated_at.to_rfc3339(),
+        expires
+    );
+    for (label, value) in [
+        ("title", url.title.clone()),
+        ("desc", url.description.clone()),
+        ("tags", Some(url.tags.join(", ")).filter(|tags| !tags.is_empty())),
+        ("notes", url.notes.clone()),
+        ("preview", url.interstitial.then(|| "always, before redirecting".to_string())),
+        ("utm", Some(utm_query(&url.utm)).filter(|utm| !utm.is_empty())),
+        ("forward", url.passthrough.then(|| "query string and path".to_string())),
+        ("from", url.active_from.map(|active_from| active_from.to_rfc3339())),
+        ("until", url.active_until.map(|active_until| active_until.to_rfc3339())),
+        ("fallback", url.fallback_url.clone()),
+        ("template", url.template.then(|| template_placeholders(&url.long_url).join(", "))),
+    ] {
+        if let Some(value) = value {
+            described.push_str(&format!("\n{:<9} {}", format!("{}:", label), value));
+        }
+    }
+    for rule in url.redirect_rules.iter() {
+        let mut conditions = serde_json::to_value(rule).unwrap_or_default();
+        if let Some(conditions) = conditions.as_object_mut() {
+            conditions.remove("url");
+        }
+        described.push_str(&format!("\nrule:     {} -> {}", conditions, rule.url));
+    }
+    for variant in url.variants.iter() {
+        let locked = url.locked_variant.as_deref() == Some(variant.name.as_str());
+        described.push_str(&format!(
+            "\nvariant:  {} (weight {}{}) -> {}",
+            variant.name,
+            variant.weight,
+            if locked { ", locked" } else { "" },
+            variant.url
+        ));
+    }
+    if let Some(page) = &url.page_metadata {
+        let page = match &page.error {
+            Some(error) => format!("not fetched ({})", error),
+            None => page.title.clone().unwrap_or_else(|| "(untitled)".to_string()),
+        };
+        described.push_str(&format!("\npage:     {}", page));
+    }
+    described
+}
+
+fn parse_redirect_rules(value: &str) -> std::result::Result<RedirectRules, String> {
+    serde_json::from_str(value).map_err(|e| e.to_string())
+}
+
+fn parse_variants(value: &str) -> std::result::Result<Variants, String> {
+    serde_json::from_str(value).map_err(|e| e.to_string())
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio_util::task::TaskTracker;
+
+/// Tracks fire-and-forget work such as click count updates so it can be
+/// drained on shutdown.
+///
+/// Tasks run on the given runtime (the main one) rather than on the actix
+/// worker that spawned them, since worker runtimes are torn down as soon as
+/// the server stops.
+#[derive(Clone)]
+pub struct BackgroundTasks {
+    handle: Handle,
+    tracker: TaskTracker,
+    pending: Arc<Mutex<HashMap<&'static str, usize>>>,
+}
+
+/// Outcome of `BackgroundTasks::drain`
+#[derive(Debug, Default)]
+pub struct DrainSummary {
+    pub completed: usize,
+    /// Unfinished tasks at the deadline, by task name
+    pub abandoned: HashMap<&'static str, usize>,
+}
+
+impl BackgroundTasks {
+    /// Create a tracker spawning onto the runtime behind `handle`
+    pub fn new(handle: Handle) -> Self {
+        Self {
+            handle,
+            tracker: TaskTracker::new(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Spawn a named task; failures should be logged by the task itself
+    pub fn spawn<F>(&self, name: &'static str, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        *self.lock_pending().entry(name).or_default() += 1;
+
+        let pending = Arc::clone(&self.pending);
+        self.tracker.spawn_on(
+            async move {
+                task.await;
+                let mut pending = pending.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(count) = pending.get_mut(name) {
+                    *count = count.saturating_sub(1);
+                }
+            },
+            &self.handle,
+        );
+    }
+
+    /// Number of tasks still running
+    pub fn len(&self) -> usize {
+        self.tracker.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracker.is_empty()
+    }
+
+    /// Wait up to `timeout` for running tasks to finish
+    pub async fn drain(&self, timeout: Duration) -> DrainSummary {
+        let started_with = self.len();
+        self.tracker.close();
+        // Unfinished tasks are dropped with the runtime, so report them here
+        let _ = tokio::time::timeout(timeout, self.tracker.wait()).await;
+
+        let abandoned = self
+            .lock_pending()
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(name, count)| (*name, *count))
+            .collect();
+
+        DrainSummary {
+            completed: started_with.saturating_sub(self.len()),
+            abandoned,
+        }
+    }
+
+    fn lock_pending(&self) -> std::sync::MutexGuard<'_, HashMap<&'static str, usize>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,166 @@
+use crate::models::{AppError, Result};
+use actix_web::http::{header, Method};
+use actix_web::HttpRequest;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// `User-Agent` substrings of link unfurlers, crawlers, uptime checkers and
+/// HTTP libraries, matched case-insensitively; the first match names the bot,
+/// so generic patterns come last
+const DEFAULT_PATTERNS: &[&str] = &[
+    // Link previews in chat and social apps
+    "slackbot",
+    "slack-imgproxy",
+    "twitterbot",
+    "facebookexternalhit",
+    "facebookcatalog",
+    "linkedinbot",
+    "discordbot",
+    "telegrambot",
+    "whatsapp",
+    "skypeuripreview",
+    "microsoftpreview",
+    "pinterestbot",
+    "redditbot",
+    "embedly",
+    "iframely",
+    "vkshare",
+    "applebot",
+    "bitlybot",
+    // Search engines and crawlers
+    "googlebot",
+    "google-inspectiontool",
+    "adsbot-google",
+    "bingbot",
+    "bingpreview",
+    "duckduckbot",
+    "yandex",
+    "baiduspider",
+    "petalbot",
+    "ahrefsbot",
+    "semrushbot",
+    "mj12bot",
+    "dotbot",
+    "ccbot",
+    "gptbot",
+    "bytespider",
+    "crawler",
+    "spider",
+    "bot/",
+    "bot;",
+    "headlesschrome",
+    // Uptime checks and link scanners
+    "uptimerobot",
+    "pingdom",
+    "statuscake",
+    "site24x7",
+    "datadog",
+    "newrelicpinger",
+    "better uptime",
+    "checkly",
+    // HTTP clients
+    "curl/",
+    "wget/",
+    "python-requests",
+    "python-urllib",
+    "aiohttp",
+    "go-http-client",
+    "java/",
+    "okhttp",
+    "apache-httpclient",
+    "node-fetch",
+    "axios/",
+    "libwww-perl",
+];
+
+/// Tells automated clients from people, so their visits can be kept out of
+/// click counts
+///
+/// Requests count as automated by `User-Agent` pattern, when they have no
+/// agent at all, when they are `HEAD` requests and when the browser marks them
+/// as prefetches. Patterns from a file are checked before the built-in ones
+/// and can be re-read while the server runs.
+pub struct BotDetector {
+    patterns_file: Option<PathBuf>,
+    patterns: RwLock<Vec<String>>,
+}
+
+impl BotDetector {
+    /// Built-in patterns plus those in `patterns_file`, one per line; blank
+    /// lines and lines starting with `#` are skipped
+    pub fn new(patterns_file: Option<&Path>) -> Result<Self> {
+        let detector = Self {
+            patterns_file: patterns_file.map(Path::to_path_buf),
+            patterns: RwLock::new(Vec::new()),
+        };
+        detector.reload()?;
+        Ok(detector)
+    }
+
+    /// Re-read the patterns file, keeping the current patterns when it cannot
+    /// be read; returns the number of patterns in use
+    pub fn reload(&self) -> Result<usize> {
+        let mut patterns: Vec<String> = Vec::new();
+        if let Some(path) = &self.patterns_file {
+            let contents = std::fs::read_to_string(path).map_err(|e| {
+                AppError::Internal(format!("Failed to read bot patterns '{}': {}", path.display(), e))
+            })?;
+            patterns.extend(
+                contents
+                    .lines()
+                    .map(|line| line.trim().to_lowercase())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#')),
+            );
+        }
+        patterns.extend(DEFAULT_PATTERNS.iter().map(|pattern| pattern.to_string()));
+        let mut seen = HashSet::new();
+        patterns.retain(|pattern| seen.insert(pattern.clone()));
+
+        let count = patterns.len();
+        *self.patterns.write().unwrap_or_else(|e| e.into_inner()) = patterns;
+        Ok(count)
+    }
+
+    pub fn patterns_file(&self) -> Option<&Path> {
+        self.patterns_file.as_deref()
+    }
+
+    /// What gives a request away as automated: the matching pattern, `head`,
+    /// `prefetch` or `no-user-agent`; `None` for people
+    pub fn classify(&self, req: &HttpRequest) -> Option<String> {
+        if req.method() == Method::HEAD {
+            return Some("head".to_string());
+        }
+
+        let headers = req.headers();
+        let has_value = |name: &str, value: &str| {
+            headers
+                .get(name)
+                .and_then(|header| header.to_str().ok())
+                .is_some_and(|header| header.to_ascii_lowercase().contains(value))
+        };
+        if has_value("sec-purpose", "prefetch")
+            || has_value("purpose", "prefetch")
+            || has_value("x-purpose", "preview")
+            || has_value("x-moz", "prefetch")
+        {
+            return Some("prefetch".to_string());
+        }
+
+        let Some(user_agent) = headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_lowercase)
+            .filter(|agent| !agent.trim().is_empty())
+        else {
+            return Some("no-user-agent".to_string());
+        };
+        self.patterns
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|pattern| user_agent.contains(pattern.as_str()))
+            .cloned()
+    }
+}
@@ -1,33 +1,40 @@
-use crate::models::Result;
-use log::{error, info};
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use crate::core::config::DatabaseConfig;
+use crate::core::metrics;
+use crate::models::{AppError, Result};
+use tracing::{error, info};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 /// High-performance database connection manager
 pub struct DatabaseManager {
     pool: Arc<PgPool>,
+    migrated: AtomicBool,
 }
 
 impl DatabaseManager {
-    pub async fn new(
-        username: &str,
-        password: &str,
-        host: &str,
-        port: u16,
-        database: &str,
-    ) -> Result<Self> {
-        let database_url = format!(
-            "postgres://{}:{}@{}:{}/{}",
-            username, password, host, port, database
-        );
+    pub async fn new(config: &DatabaseConfig) -> Result<Self> {
+        // A full URL wins over the individual fields
+        let options = match &config.url {
+            Some(url) => PgConnectOptions::from_str(url)?,
+            None => PgConnectOptions::new()
+                .host(&config.host)
+                .port(config.port)
+                .username(&config.user)
+                .password(&config.password)
+                .database(&config.name),
+        };
 
         let pool = PgPoolOptions::new()
-            .max_connections(20) // Increased for better performance
-            .min_connections(5)
-            .max_lifetime(Some(Duration::from_secs(3600))) // 1 hour
-            .idle_timeout(Some(Duration::from_secs(600))) // 10 minutes
-            .connect(&database_url)
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(Duration::from_secs(config.connect_timeout_secs))
+            .max_lifetime(Some(Duration::from_secs(config.max_lifetime_secs)))
+            .idle_timeout(Some(Duration::from_secs(config.idle_timeout_secs)))
+            .connect_with(options)
             .await
             .map_err(|e| {
                 error!("Failed to connect to database: {}", e);
@@ -38,6 +45,7 @@ impl DatabaseManager {
 
         Ok(Self {
             pool: Arc::new(pool),
+            migrated: AtomicBool::new(false),
         })
     }
 
@@ -45,6 +53,18 @@ impl DatabaseManager {
         Arc::clone(&self.pool)
     }
 
+    /// Publish pool utilization to the metrics registry
+    pub fn record_pool_metrics(&self) {
+        let size = self.pool.size() as i64;
+        let idle = self.pool.num_idle() as i64;
+
+        metrics::DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+        metrics::DB_POOL_CONNECTIONS
+            .with_label_values(&["active"])
+            .set((size - idle).max(0));
+        metrics::DB_POOL_MAX_CONNECTIONS.set(self.pool.options().get_max_connections() as i64);
+    }
+
     /// Test database connection
     pub async fn health_check(&self) -> Result<()> {
         sqlx::query("SELECT 1")
@@ -58,6 +78,41 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Close all pooled connections, waiting for checked-out ones to be returned
+    pub async fn close(&self) {
+        self.pool.close().await;
+        info!("Database connections closed");
+    }
+
+    /// Whether `migrate` has completed in this process
+    pub fn is_migrated(&self) -> bool {
+        self.migrated.load(Ordering::Acquire)
+    }
+
+    /// Check that the newest schema objects exist, so a database migrated by an
+    /// older release (or dropped behind our back) does not count as ready
+    pub async fn schema_check(&self) -> Result<()> {
+        let (current,): (bool,) = sqlx::query_as(
+            r#"
+            SELECT to_regclass('domains') IS NOT NULL
+               AND EXISTS (
+                   SELECT 1 FROM information_schema.columns
+                   WHERE table_schema = current_schema()
+                     AND table_name = 'tinyurls'
+                     AND column_name = 'fallback_url'
+               )
+            "#,
+        )
+        .fetch_one(&*self.pool)
+        .await?;
+
+        if current {
+            Ok(())
+        } else {
+            Err(AppError::Internal("database schema is missing the latest migrations".to_string()))
+        }
+    }
+
     /// Run database migrations
     pub async fn migrate(&self) -> Result<()> {
         info!("Running database migrations...");
@@ -68,6 +123,7 @@ impl DatabaseManager {
             .init()
             .await?;
 
+        self.migrated.store(true, Ordering::Release);
         info!("Database migrations completed successfully");
         Ok(())
     }
@@ -81,7 +137,15 @@ pub async fn init_db(
     port: u16,
     database: &str,
 ) -> Result<Arc<PgPool>> {
-    let db_manager = DatabaseManager::new(username, password, host, port, database).await?;
+    let config = DatabaseConfig {
+        host: host.to_string(),
+        port,
+        user: username.to_string(),
+        password: password.to_string(),
+        name: database.to_string(),
+        ..DatabaseConfig::default()
+    };
+    let db_manager = DatabaseManager::new(&config).await?;
     db_manager.migrate().await?;
     Ok(db_manager.get_pool())
 }
@@ -0,0 +1,92 @@
+use crate::core::db_connect::DatabaseManager;
+use crate::models::{ComponentHealth, HealthResponse, Result};
+use crate::services::RedisCacheService;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Liveness: the process is up and serving requests; dependencies are not checked
+pub fn liveness() -> HealthResponse {
+    HealthResponse {
+        status: "healthy".to_string(),
+        timestamp: Utc::now(),
+        components: BTreeMap::new(),
+    }
+}
+
+/// Readiness: check Postgres, the schema migrations and Redis, each bounded by `timeout`.
+///
+/// Migrations are `up` once this process has run them and the newest tables and
+/// columns are present in the database.
+///
+/// Postgres and migrations are hard dependencies and make the service `unhealthy`;
+/// Redis only makes it `degraded` since the in-memory cache takes over.
+pub async fn readiness(
+    db: &DatabaseManager,
+    cache: &RedisCacheService,
+    timeout: Duration,
+) -> HealthResponse {
+    let (database, migrations, redis) = tokio::join!(
+        probe(timeout, db.health_check()),
+        async {
+            if db.is_migrated() {
+                probe(timeout, db.schema_check()).await
+            } else {
+                component("down", None, Some("migrations have not completed".to_string()))
+            }
+        },
+        async {
+            if cache.has_redis() {
+                probe(timeout, cache.ping()).await
+            } else {
+                component("disabled", None, None)
+            }
+        }
+    );
+
+    let hard_down = database.status != "up" || migrations.status != "up";
+    let status = if hard_down {
+        "unhealthy"
+    } else if redis.status == "down" {
+        "degraded"
+    } else {
+        "healthy"
+    };
+
+    let mut components = BTreeMap::new();
+    components.insert("database".to_string(), database);
+    components.insert("migrations".to_string(), migrations);
+    components.insert("redis".to_string(), redis);
+
+    HealthResponse {
+        status: status.to_string(),
+        timestamp: Utc::now(),
+        components,
+    }
+}
+
+/// Run a dependency check with a deadline, timing it
+async fn probe(timeout: Duration, check: impl Future<Output = Result<()>>) -> ComponentHealth {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(timeout, check).await;
+    let latency_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+
+    match outcome {
+        Ok(Ok(())) => component("up", latency_ms, None),
+        Ok(Err(e)) => component("down", latency_ms, Some(e.to_string())),
+        Err(_) => component(
+            "down",
+            latency_ms,
+            Some(format!("timed out after {}ms", timeout.as_millis())),
+        ),
+    }
+}
+
+fn component(status: &str, latency_ms: Option<f64>, error: Option<String>) -> ComponentHealth {
+    ComponentHealth {
+        status: status.to_string(),
+        latency_ms,
+        error,
+    }
+}
@@ -0,0 +1,160 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use std::time::Instant;
+
+/// Registry holding all service metrics, rendered by `/metrics`
+pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+/// HTTP requests by method, matched route pattern and status code
+pub static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new("tinyurl_http_requests_total", "HTTP requests handled"),
+        &["method", "route", "status"],
+    ))
+});
+
+/// HTTP request latency by method and matched route pattern
+pub static HTTP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "tinyurl_http_request_duration_seconds",
+            "HTTP request latency in seconds",
+        )
+        .buckets(vec![
+            0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+        ]),
+        &["method", "route"],
+    ))
+});
+
+/// Redirect lookups by outcome: `hit` (cache), `miss` (database) or `not_found`
+pub static REDIRECTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new("tinyurl_redirects_total", "Short code resolutions by outcome"),
+        &["outcome"],
+    ))
+});
+
+/// Cache reads by result: `hit` or `miss`
+pub static CACHE_LOOKUPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new("tinyurl_cache_lookups_total", "Cache reads by result"),
+        &["result"],
+    ))
+});
+
+/// Cache operations served by the in-memory fallback because Redis was unavailable or failed
+pub static CACHE_FALLBACKS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "tinyurl_cache_fallbacks_total",
+            "Cache operations served by the in-memory fallback",
+        ),
+        &["operation"],
+    ))
+});
+
+/// Database pool connections by state: `idle` or `active`
+pub static DB_POOL_CONNECTIONS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register(IntGaugeVec::new(
+        Opts::new("tinyurl_db_pool_connections", "Database pool connections by state"),
+        &["state"],
+    ))
+});
+
+/// Configured maximum database pool size
+pub static DB_POOL_MAX_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register(IntGauge::new(
+        "tinyurl_db_pool_max_connections",
+        "Maximum database pool size",
+    ))
+});
+
+/// Generated short codes discarded because they already existed
+pub static SHORT_CODE_COLLISIONS: Lazy<IntCounter> = Lazy::new(|| {
+    register(IntCounter::new(
+        "tinyurl_short_code_collisions_total",
+        "Generated short codes retried after a collision",
+    ))
+});
+
+/// Failed background tasks by task name
+pub static BACKGROUND_TASK_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new("tinyurl_background_task_errors_total", "Failed background tasks"),
+        &["task"],
+    ))
+});
+
+/// Destination page metadata fetches by outcome: `ok` or `error`
+pub static PAGE_FETCHES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new("tinyurl_page_fetches_total", "Destination page metadata fetches"),
+        &["outcome"],
+    ))
+});
+
+/// Register a metric in `REGISTRY`; metric definitions are static, so failures are bugs
+fn register<M>(metric: prometheus::Result<M>) -> M
+where
+    M: prometheus::core::Collector + Clone + 'static,
+{
+    let metric = metric.expect("invalid metric definition");
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("duplicate metric registration");
+    metric
+}
+
+/// Render all metrics in the Prometheus text exposition format
+pub fn render() -> String {
+    // Touch lazily registered metrics so they appear before their first update
+    Lazy::force(&HTTP_REQUESTS);
+    Lazy::force(&HTTP_REQUEST_DURATION);
+    Lazy::force(&REDIRECTS);
+    Lazy::force(&CACHE_LOOKUPS);
+    Lazy::force(&CACHE_FALLBACKS);
+    Lazy::force(&DB_POOL_CONNECTIONS);
+    Lazy::force(&DB_POOL_MAX_CONNECTIONS);
+    Lazy::force(&SHORT_CODE_COLLISIONS);
+    Lazy::force(&BACKGROUND_TASK_ERRORS);
+    Lazy::force(&PAGE_FETCHES);
+
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+        tracing::error!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+/// Middleware recording request counts and latency per matched route
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+
+    let result = next.call(req).await;
+
+    let status = match &result {
+        Ok(response) => response.status().as_u16().to_string(),
+        Err(e) => e.as_response_error().status_code().as_u16().to_string(),
+    };
+    HTTP_REQUESTS
+        .with_label_values(&[&method, &route, &status])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[&method, &route])
+        .observe(started.elapsed().as_secs_f64());
+
+    result
+}
@@ -1,2 +1,10 @@
+pub mod background;
+pub mod bots;
 pub mod config;
 pub mod db_connect;
+pub mod health;
+pub mod metrics;
+pub mod preview;
+pub mod public_url;
+pub mod request_id;
+pub mod telemetry;
@@ -0,0 +1,110 @@
+use crate::models::LinkPreview;
+use actix_web::http::header;
+use actix_web::HttpResponse;
+
+/// Preview page of a link: where it goes, `location` for this visitor,
+/// without going there
+///
+/// The page loads nothing from the destination, so viewing it does not
+/// reveal the visitor to the destination site.
+pub fn preview_response(link: &LinkPreview, location: &str) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .insert_header((header::REFERRER_POLICY, "no-referrer"))
+        .insert_header((
+            header::CONTENT_SECURITY_POLICY,
+            "default-src 'none'; style-src 'unsafe-inline'",
+        ))
+        .insert_header(("X-Robots-Tag", "noindex"))
+        .body(render(link, location))
+}
+
+fn render(link: &LinkPreview, location: &str) -> String {
+    // Fetched page metadata describes `long_url`, not a per-platform destination
+    let page = link.page_metadata.as_ref().filter(|_| location == link.long_url);
+    let host = url::Url::parse(location)
+        .ok()
+        .and_then(|destination| destination.host_str().map(str::to_string))
+        .unwrap_or_default();
+    let title = link
+        .title
+        .as_deref()
+        .or(page.and_then(|page| page.title.as_deref()))
+        .unwrap_or(&host);
+    let description = link
+        .description
+        .as_deref()
+        .or(page.and_then(|page| page.description.as_deref()));
+    // The destination itself redirecting elsewhere is worth knowing before clicking
+    let redirect_host = page
+        .and_then(|page| page.final_url.as_deref())
+        .and_then(|final_url| url::Url::parse(final_url).ok())
+        .and_then(|final_url| final_url.host_str().map(str::to_string))
+        .filter(|final_host| *final_host != host);
+
+    let mut details = String::new();
+    if let Some(description) = description {
+        details.push_str(&format!("<p>{}</p>\n", escape_html(description)));
+    }
+    details.push_str(&format!(
+        "<dl>\n<dt>Goes to</dt><dd><strong>{}</strong></dd>\n<dt>Full address</dt><dd class=\"url\">{}</dd>\n",
+        escape_html(&host),
+        escape_html(location)
+    ));
+    if let Some(redirect_host) = redirect_host {
+        details.push_str(&format!(
+            "<dt>Redirects to</dt><dd><strong>{}</strong></dd>\n",
+            escape_html(&redirect_host)
+        ));
+    }
+    details.push_str(&format!(
+        "<dt>Created</dt><dd>{}</dd>\n</dl>\n",
+        link.created_at.format("%B %-d, %Y")
+    ));
+
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>Preview: {title}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; max-width: 40rem; margin: 3rem auto; padding: 0 1rem; color: #222; }}
+h1 {{ font-size: 1.4rem; overflow-wrap: anywhere; }}
+dt {{ color: #666; font-size: 0.85rem; margin-top: 0.8rem; }}
+dd {{ margin: 0.2rem 0 0; }}
+.url {{ font-family: ui-monospace, monospace; overflow-wrap: anywhere; }}
+a.continue {{ display: inline-block; margin-top: 1.5rem; padding: 0.6rem 1.2rem; background: #1a5fb4; color: #fff; border-radius: 4px; text-decoration: none; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+{details}<a class="continue" href="{href}" rel="noreferrer">Continue to {host}</a>
+</body>
+</html>
+"#,
+        title = escape_html(title),
+        details = details,
+        href = escape_html(location),
+        host = escape_html(&host),
+    )
+}
+
+/// Escape text for HTML element content and quoted attribute values
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
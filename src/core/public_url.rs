@@ -0,0 +1,262 @@
+use crate::models::{AppError, Result};
+use actix_web::http::header;
+use actix_web::HttpRequest;
+use std::net::IpAddr;
+use url::Url;
+
+/// Public-facing URL settings: the external base URL, the path prefix routes
+/// are mounted under, and the proxies allowed to set `X-Forwarded-*` headers.
+#[derive(Debug, Clone)]
+pub struct PublicUrl {
+    base_url: Option<String>,
+    /// Base URL without `PUBLIC_BASE_URL` or a trusted forwarded host, from the
+    /// server's own address; a client's `Host` header is never trusted for it
+    server_base_url: String,
+    path_prefix: String,
+    trusted_proxies: Vec<ProxyRange>,
+}
+
+impl PublicUrl {
+    /// Build from an optional `PUBLIC_BASE_URL` and a list of trusted proxy IPs or CIDR ranges
+    pub fn new(public_base_url: Option<&str>, trusted_proxies: &[String]) -> Result<Self> {
+        let trusted_proxies = trusted_proxies
+            .iter()
+            .map(|proxy| ProxyRange::parse(proxy))
+            .collect::<Result<Vec<_>>>()?;
+
+        let Some(raw) = public_base_url else {
+            return Ok(Self {
+                base_url: None,
+                server_base_url: "http://localhost".to_string(),
+                path_prefix: String::new(),
+                trusted_proxies,
+            });
+        };
+
+        let url = Url::parse(raw)
+            .map_err(|e| AppError::Validation(format!("Invalid PUBLIC_BASE_URL '{}': {}", raw, e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(AppError::Validation(
+                "PUBLIC_BASE_URL must use http or https".to_string(),
+            ));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| AppError::Validation("PUBLIC_BASE_URL must include a host".to_string()))?;
+        if url.query().is_some() || url.fragment().is_some() {
+            return Err(AppError::Validation(
+                "PUBLIC_BASE_URL cannot have a query or fragment".to_string(),
+            ));
+        }
+
+        let path_prefix = url.path().trim_end_matches('/').to_string();
+        let authority = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+
+        let base_url = format!("{}://{}{}", url.scheme(), authority, path_prefix);
+        Ok(Self {
+            server_base_url: base_url.clone(),
+            base_url: Some(base_url),
+            path_prefix,
+            trusted_proxies,
+        })
+    }
+
+    /// Fall back to the address the server listens on for short URLs when no
+    /// public base URL is configured; unspecified bind addresses become `localhost`
+    pub fn with_server_address(mut self, host: &str, port: u16) -> Self {
+        if self.base_url.is_some() {
+            return self;
+        }
+        let host = match host.parse::<IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => "localhost".to_string(),
+            Ok(IpAddr::V6(ip)) => format!("[{}]", ip),
+            _ => host.to_string(),
+        };
+        self.server_base_url = match port {
+            80 => format!("http://{}", host),
+            443 => format!("https://{}", host),
+            port => format!("http://{}:{}", host, port),
+        };
+        self
+    }
+
+    /// Path prefix routes are mounted under, empty or starting with `/`
+    pub fn path_prefix(&self) -> &str {
+        &self.path_prefix
+    }
+
+    /// Configured public base URL, if any
+    pub fn configured_base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+
+    /// Base URL for short URLs created by this request: the configured one,
+    /// else the scheme and host forwarded by a trusted proxy, else the server's
+    /// own address. The `Host` header alone is not used, as any client sets it
+    pub fn base_url(&self, req: &HttpRequest) -> String {
+        if let Some(base_url) = &self.base_url {
+            return base_url.clone();
+        }
+        match self.forwarded_header(req, "x-forwarded-host") {
+            Some(host) => format!("{}://{}{}", self.request_scheme(req), host, self.path_prefix),
+            None => self.server_base_url.clone(),
+        }
+    }
+
+    /// Host the client addressed, honoring `X-Forwarded-Host` from trusted proxies
+    pub fn request_host(&self, req: &HttpRequest) -> String {
+        if let Some(host) = self.forwarded_header(req, "x-forwarded-host") {
+            return host;
+        }
+
+        req.headers()
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| req.app_config().host().to_string())
+    }
+
+    /// Scheme the client used, honoring `X-Forwarded-Proto` from trusted proxies
+    pub fn request_scheme(&self, req: &HttpRequest) -> String {
+        if let Some(proto) = self.forwarded_header(req, "x-forwarded-proto") {
+            let proto = proto.to_ascii_lowercase();
+            if proto == "http" || proto == "https" {
+                return proto;
+            }
+        }
+
+        if req.app_config().secure() { "https" } else { "http" }.to_string()
+    }
+
+    /// Address of the client: the last `X-Forwarded-For` hop not added by a
+    /// trusted proxy, or the direct peer when it is not a trusted proxy
+    pub fn client_ip(&self, req: &HttpRequest) -> Option<IpAddr> {
+        let peer = req.peer_addr()?.ip();
+        if !self.is_trusted(peer) {
+            return Some(peer);
+        }
+
+        // Each proxy appends the address it received from, so only the
+        // rightmost entries can be believed: stop at the first one that is
+        // not a trusted proxy, or at anything unparseable
+        let hops: Vec<&str> = req
+            .headers()
+            .get_all("x-forwarded-for")
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+        let mut client = peer;
+        for hop in hops.into_iter().rev() {
+            let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !self.is_trusted(ip) {
+                break;
+            }
+        }
+        Some(client)
+    }
+
+    /// Whether the direct peer is a trusted proxy
+    pub fn is_trusted_peer(&self, req: &HttpRequest) -> bool {
+        req.peer_addr().is_some_and(|addr| self.is_trusted(addr.ip()))
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|range| range.contains(ip))
+    }
+
+    /// First value of a forwarded header, only when set by a trusted proxy
+    fn forwarded_header(&self, req: &HttpRequest, name: &str) -> Option<String> {
+        if !self.is_trusted_peer(req) {
+            return None;
+        }
+
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// IP address or CIDR range of a trusted proxy
+#[derive(Debug, Clone, Copy)]
+struct ProxyRange {
+    network: IpAddr,
+    prefix_len: u32,
+}
+
+impl ProxyRange {
+    fn parse(value: &str) -> Result<Self> {
+        let invalid = || AppError::Validation(format!("Invalid trusted proxy '{}'", value));
+        let (addr, prefix) = match value.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value.trim(), None),
+        };
+
+        let network: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix {
+            Some(prefix) => prefix.parse().map_err(|_| invalid())?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(invalid());
+        }
+
+        Ok(Self { network, prefix_len })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        // Compare IPv4-mapped IPv6 peers as IPv4
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn base_url_ignores_untrusted_host_headers() {
+        let public_url = PublicUrl::new(None, &["10.0.0.1".to_string()])
+            .unwrap()
+            .with_server_address("0.0.0.0", 8080);
+
+        let spoofed = TestRequest::default()
+            .insert_header((header::HOST, "evil.example"))
+            .insert_header(("x-forwarded-host", "evil.example"))
+            .peer_addr("192.0.2.1:4000".parse().unwrap())
+            .to_http_request();
+        assert_eq!(public_url.base_url(&spoofed), "http://localhost:8080");
+
+        let proxied = TestRequest::default()
+            .insert_header(("x-forwarded-host", "sho.rt"))
+            .insert_header(("x-forwarded-proto", "https"))
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .to_http_request();
+        assert_eq!(public_url.base_url(&proxied), "https://sho.rt");
+    }
+}
@@ -0,0 +1,166 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage};
+use std::net::IpAddr;
+use std::time::Instant;
+use tracing::field::Empty;
+use tracing::Span;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+/// Header carrying the request ID, accepted from clients and echoed in responses
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied request ID accepted as is
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Request ID stored in the request extensions by `assign`
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// ID of the request being handled on this task, if any
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Middleware accepting a valid `X-Request-Id` or generating one, exposing it to
+/// handlers and the root span, echoing it in the response and writing the access log
+pub async fn assign(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let client_ip = masked_peer_ip(&req);
+
+    let mut result = REQUEST_ID.scope(request_id.clone(), next.call(req)).await;
+
+    let status = match &mut result {
+        Ok(response) => {
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                response
+                    .headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            response.status().as_u16()
+        }
+        Err(e) => e.as_response_error().status_code().as_u16(),
+    };
+
+    // Query strings may carry tokens, so only the path is logged
+    tracing::info!(
+        target: "access",
+        request_id = %request_id,
+        method = %method,
+        path = %path,
+        status,
+        latency_ms = started.elapsed().as_secs_f64() * 1000.0,
+        client_ip = %client_ip,
+        "request completed"
+    );
+
+    result
+}
+
+/// Root span for `TracingLogger` carrying our request ID and a masked client IP,
+/// continuing any W3C trace context sent by the caller
+pub struct RequestIdRootSpan;
+
+impl RootSpanBuilder for RequestIdRootSpan {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let request_id = request
+            .extensions()
+            .get::<RequestId>()
+            .map(|id| id.0.clone())
+            .unwrap_or_default();
+        let method = request.method().as_str();
+        let route = request.match_pattern().unwrap_or_else(|| "default".to_string());
+
+        let span = tracing::info_span!(
+            "HTTP request",
+            http.method = %method,
+            http.route = %route,
+            http.target = %request.path(),
+            http.client_ip = %masked_peer_ip(request),
+            http.status_code = Empty,
+            otel.name = %format!("{} {}", method, route),
+            otel.kind = "server",
+            otel.status_code = Empty,
+            request_id = %request_id,
+            exception.message = Empty,
+            exception.details = Empty,
+        );
+
+        let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(request.headers()))
+        });
+        let _ = span.set_parent(parent);
+
+        span
+    }
+
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+/// Read-only view of request headers for the trace context propagator
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl opentelemetry::propagation::Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+fn masked_peer_ip(req: &ServiceRequest) -> String {
+    req.peer_addr()
+        .map(|addr| mask_ip(addr.ip()))
+        .unwrap_or_default()
+}
+
+/// Truncate an IP for logging: IPv4 to its /24, IPv6 to its /48
+pub fn mask_ip(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            format!("{}.{}.{}.0", a, b, c)
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => mask_ip(IpAddr::V4(v4)),
+            None => {
+                let segments = v6.segments();
+                format!("{:x}:{:x}:{:x}::", segments[0], segments[1], segments[2])
+            }
+        },
+    }
+}
@@ -0,0 +1,207 @@
+use crate::core::config::{LogFormat, LoggingConfig, TelemetryConfig};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use std::time::Duration;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::Layer;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Keeps the OTLP exporter alive; call `shutdown` before exit to flush pending spans
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl TelemetryGuard {
+    /// Flush and stop the span exporter, if enabled
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to shut down trace exporter: {}", e);
+            }
+        }
+    }
+}
+
+/// Install the global `tracing` subscriber: `RUST_LOG` filtering, text or JSON
+/// console output, and OTLP span export when enabled. `log` records from
+/// dependencies are forwarded to the same subscriber.
+pub fn init(
+    config: &TelemetryConfig,
+    logging: &LoggingConfig,
+    app: &str,
+) -> Result<TelemetryGuard, String> {
+    // W3C `traceparent`/`tracestate` headers are extracted by the request middleware
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let provider = if config.otlp_enabled {
+        Some(build_provider(config, app)?)
+    } else {
+        None
+    };
+    let otel_layer = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("tinyurl-rs")));
+
+    // JSON lines carry the fields of every enclosing span, so `request_id` from
+    // the root request span appears on each line logged while handling it
+    let fmt_layer = match logging.format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+    };
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(otel_layer)
+        .with(filter)
+        .try_init()
+        .map_err(|e| format!("Failed to install tracing subscriber: {}", e))?;
+
+    if let Some(provider) = &provider {
+        opentelemetry::global::set_tracer_provider(provider.clone());
+        tracing::info!("Exporting traces to {}", config.otlp_endpoint);
+    }
+
+    Ok(TelemetryGuard { provider })
+}
+
+/// Tracer provider batching spans to the OTLP/HTTP endpoint
+fn build_provider(config: &TelemetryConfig, app: &str) -> Result<SdkTracerProvider, String> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&config.otlp_endpoint)
+        .with_timeout(Duration::from_secs(config.export_timeout_secs))
+        .build()
+        .map_err(|e| format!("Failed to build OTLP exporter: {}", e))?;
+
+    let service_name = config.service_name.clone().unwrap_or_else(|| app.to_string());
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sample_ratio,
+        ))))
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::request_id::{self, RequestIdRootSpan};
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse, HttpServer};
+    use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+    use opentelemetry_proto::tonic::trace::v1::Span;
+    use prost::Message;
+    use std::sync::{Arc, Mutex};
+    use tracing_actix_web::TracingLogger;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+    type Received = Arc<Mutex<Vec<Span>>>;
+
+    /// Stand-in OTLP/HTTP receiver on a random loopback port, keeping every span sent to it
+    async fn start_receiver() -> (String, Received) {
+        let received = Received::default();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+
+        let spans = web::Data::new(Arc::clone(&received));
+        let server = HttpServer::new(move || {
+            App::new().app_data(spans.clone()).route(
+                "/v1/traces",
+                web::post().to(|body: web::Bytes, spans: web::Data<Received>| async move {
+                    let request = ExportTraceServiceRequest::decode(body).unwrap();
+                    spans.lock().unwrap().extend(
+                        request
+                            .resource_spans
+                            .into_iter()
+                            .flat_map(|resource| resource.scope_spans)
+                            .flat_map(|scope| scope.spans),
+                    );
+                    HttpResponse::Ok().finish()
+                }),
+            )
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        tokio::spawn(server);
+        (endpoint, received)
+    }
+
+    #[tracing::instrument(name = "route.traced")]
+    async fn traced() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    fn hex_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn exports_route_spans_in_the_propagated_trace() {
+        let (endpoint, received) = start_receiver().await;
+        // Nothing is sampled on its own, so only the sampled incoming parent gets spans exported
+        let config = TelemetryConfig {
+            otlp_enabled: true,
+            otlp_endpoint: endpoint,
+            sample_ratio: 0.0,
+            ..TelemetryConfig::default()
+        };
+        let provider = build_provider(&config, "tinyurl-test").unwrap();
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("tinyurl-rs")));
+        let subscriber_guard = tracing::subscriber::set_default(subscriber);
+
+        let app = test::init_service(
+            App::new()
+                .wrap(TracingLogger::<RequestIdRootSpan>::new())
+                .wrap(from_fn(request_id::assign))
+                .route("/traced", web::get().to(traced)),
+        )
+        .await;
+        let request = test::TestRequest::get()
+            .uri("/traced")
+            .insert_header(("traceparent", format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID)))
+            .to_request();
+        assert!(test::call_service(&app, request).await.status().is_success());
+        drop(subscriber_guard);
+
+        // Flushing blocks until the receiver has answered
+        let flushed = provider.clone();
+        tokio::task::spawn_blocking(move || flushed.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+        provider.shutdown().unwrap();
+
+        let spans = received.lock().unwrap();
+        let span = |name: &str| {
+            spans
+                .iter()
+                .find(|span| span.name == name)
+                .unwrap_or_else(|| panic!("no '{}' span among {:?}", name, spans))
+        };
+        let (root, route) = (span("GET /traced"), span("route.traced"));
+        assert_eq!(root.trace_id, hex_bytes(TRACE_ID));
+        assert_eq!(root.parent_span_id, hex_bytes(PARENT_SPAN_ID));
+        assert_eq!(route.trace_id, hex_bytes(TRACE_ID));
+        assert_eq!(route.parent_span_id, root.span_id);
+    }
+}
@@ -1,41 +1,60 @@
-use actix_web::{middleware::Logger, web, App, HttpServer};
-use env_logger::Env;
-use log::{error, info};
+use actix_web::{middleware::from_fn, web, App, HttpServer};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::{interval, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{debug, error, info, warn};
+use tracing_actix_web::TracingLogger;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-mod core;
-mod models;
-mod traits;
-mod services;
-mod repository;
-mod routes;
-
-use crate::core::config::Config;
-use crate::core::db_connect::DatabaseManager;
-use crate::repository::PostgresUrlRepository;
-use crate::routes::{configure_routes, ApiDoc, AppState};
-use crate::services::{DefaultShortCodeGenerator, DefaultUrlService, RedisCacheService};
+use tinyurl_rs::core::background::BackgroundTasks;
+use tinyurl_rs::core::bots::BotDetector;
+use tinyurl_rs::core::config::Config;
+use tinyurl_rs::core::db_connect::DatabaseManager;
+use tinyurl_rs::core::metrics;
+use tinyurl_rs::core::public_url::PublicUrl;
+use tinyurl_rs::core::request_id::{self, RequestIdRootSpan};
+use tinyurl_rs::core::telemetry;
+use tinyurl_rs::repository::PostgresUrlRepository;
+use tinyurl_rs::routes::{configure_routes, ApiDoc, AppState};
+use tinyurl_rs::services::{DefaultUrlService, HttpPageFetcher, MaxMindGeoLocator, RedisCacheService};
+use tinyurl_rs::traits::{GeoLocator, ShortCodeGenerator, UrlService};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    env_logger::init_from_env(Env::default().default_filter_or("info"));
-    
-    let config = Config::load();
-    info!("Starting TinyURL service with config: {:?}", config);
+    // Logging is configured from the config, so load errors go straight to stderr
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if std::env::args().any(|arg| arg == "--print-config") {
+        print!("{}", config.to_redacted_toml());
+        return Ok(());
+    }
+
+    let telemetry = match telemetry::init(&config.telemetry, &config.logging, &config.app) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    info!("Starting TinyURL service with config: {:?}", config.redacted());
 
     // Initialize database
     info!("Connecting to database...");
-    let db_manager = DatabaseManager::new(
-        &config.db_user,
-        &config.db_password,
-        &config.db_host,
-        config.db_port.try_into().unwrap(),
-        &config.db_name,
-    )
-    .await
-    .expect("Failed to connect to database");
+    let db_manager = Arc::new(
+        DatabaseManager::new(&config.database)
+            .await
+            .expect("Failed to connect to database"),
+    );
 
     // Run migrations
     db_manager.migrate().await.expect("Failed to run migrations");
@@ -45,49 +64,153 @@ async fn main() -> std::io::Result<()> {
     
     // Initialize cache (Redis optional)
     let cache = Arc::new(
-        RedisCacheService::new(config.redis_url.clone()).expect("Failed to initialize cache service")
+        RedisCacheService::new(config.cache.redis_url.clone(), config.cache.ttl_secs)
+            .expect("Failed to initialize cache service")
     );
     
-    let short_code_generator = Arc::new(DefaultShortCodeGenerator::new());
+    let short_code_generator = Arc::new(
+        config.short_code_strategy().expect("Invalid short code configuration"),
+    );
+    info!(
+        "Using '{}' short codes with ~{:.1} bits of entropy",
+        config.generator.style,
+        short_code_generator.entropy_bits(config.generator.length)
+    );
     
-    // Build base URL
-    let base_url = format!("{}://{}", 
-        if config.port == 443 { "https" } else { "http" }, 
-        if config.port == 80 || config.port == 443 { 
-            config.host.clone() 
-        } else { 
-            format!("{}:{}", config.host, config.port) 
-        }
+    // Public base URL and trusted proxies
+    let public_url = Arc::new(
+        PublicUrl::new(
+            config.server.public_base_url.as_deref(),
+            &config.security.trusted_proxies,
+        )
+        .expect("Invalid public URL configuration")
+        .with_server_address(&config.server.host, config.server.port),
     );
+    match public_url.configured_base_url() {
+        Some(base_url) => info!("Serving short URLs under {}", base_url),
+        None => info!(
+            "PUBLIC_BASE_URL not set, deriving short URLs from trusted proxies' X-Forwarded-Host or the server address"
+        ),
+    }
     
-    let url_service = Arc::new(DefaultUrlService::new(
+    // Click updates and other deferred writes run on this (main) runtime so
+    // they survive the worker runtimes and can be drained on shutdown
+    let background = BackgroundTasks::new(tokio::runtime::Handle::current());
+
+    // Periodic jobs and signal handlers loop until shutdown, so they are
+    // stopped before the background drain rather than drained with it
+    let jobs = TaskTracker::new();
+    let shutdown = CancellationToken::new();
+
+    let mut url_service = DefaultUrlService::new(
         repository,
-        cache,
+        Arc::clone(&cache),
         short_code_generator,
-        base_url,
-        8, // default short code length
-        3600, // cache TTL: 1 hour
-    ));
+        config.generator.length,
+        config.cache.ttl_secs,
+        chrono::Duration::days(config.generator.quarantine_days.into()),
+        background.clone(),
+    )
+    .with_hourly_click_retention(chrono::Duration::days(config.analytics.hourly_retention_days.into()));
+    if config.page_fetch.enabled {
+        let page_fetcher = HttpPageFetcher::new(&config.page_fetch)
+            .expect("Failed to initialize page metadata fetcher");
+        url_service = url_service.with_page_fetcher(Arc::new(page_fetcher));
+    }
+    let url_service = Arc::new(url_service);
+
+    // Visitor countries for geo-targeted redirects
+    let geoip = config.geoip.database_path.as_deref().map(|path| {
+        let locator = MaxMindGeoLocator::open(path).expect("Failed to load GeoIP database");
+        info!("Using GeoIP database {} ({})", path.display(), locator.describe());
+        Arc::new(locator) as Arc<dyn GeoLocator + Send + Sync>
+    });
+
+    // Bots are redirected but kept out of click counts
+    let bots = Arc::new(
+        BotDetector::new(config.bots.patterns_file.as_deref()).expect("Failed to load bot patterns"),
+    );
+    if let Some(path) = bots.patterns_file() {
+        info!("Using bot patterns from {}, re-read on SIGHUP", path.display());
+        let bots = Arc::clone(&bots);
+        let mut hangups = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+        let shutdown = shutdown.clone();
+        jobs.spawn(async move {
+            while let Some(()) = tokio::select! {
+                _ = shutdown.cancelled() => None,
+                hangup = hangups.recv() => hangup,
+            } {
+                match bots.reload() {
+                    Ok(count) => info!("Reloaded bot patterns, {} in use", count),
+                    Err(e) => warn!("Keeping the current bot patterns: {}", e),
+                }
+            }
+        });
+    }
+
+    // Raw clicks become hourly and daily counts for the click time series
+    if config.analytics.rollup_interval_secs > 0 {
+        let url_service = Arc::clone(&url_service);
+        let analytics = config.analytics.clone();
+        let mut ticks = interval(Duration::from_secs(analytics.rollup_interval_secs));
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let shutdown = shutdown.clone();
+        jobs.spawn(async move {
+            loop {
+                // A roll-up in progress finishes; only the wait for the next one is cut short
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticks.tick() => {}
+                }
+                let (events_before, hours_before) = analytics.retention_cutoffs(chrono::Utc::now());
+                match url_service.roll_up_clicks(events_before, hours_before).await {
+                    Ok(report) => debug!(
+                        "Rolled up {} clicks, pruned {} raw clicks and {} hourly counts",
+                        report.rolled_up, report.pruned_events, report.pruned_hours
+                    ),
+                    Err(e) => {
+                        error!("Failed to roll up clicks: {}", e);
+                        metrics::BACKGROUND_TASK_ERRORS.with_label_values(&["click_rollup"]).inc();
+                    }
+                }
+            }
+        });
+    }
 
     // Create app state
-    let app_state = AppState { url_service };
+    let app_state = AppState {
+        db: Arc::clone(&db_manager),
+        cache,
+        public_url: Arc::clone(&public_url),
+        readiness_timeout: Duration::from_millis(config.server.readiness_timeout_ms),
+        admin_token: config.security.admin_token.as_deref().map(Arc::from),
+        geoip,
+        bots,
+        url_service,
+    };
 
-    info!("Starting server on {}:{}", config.host, config.port);
+    info!("Starting server on {}:{}", config.server.host, config.server.port);
     
+    let path_prefix = public_url.path_prefix().to_string();
     let server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(app_state.clone()))
-            .wrap(Logger::new("%a %r %s %b %T"))
+            .wrap(TracingLogger::<RequestIdRootSpan>::new())
+            .wrap(from_fn(metrics::track_requests))
+            // Outermost, so the ID exists before the root span is opened
+            .wrap(from_fn(request_id::assign))
             .service(
-                SwaggerUi::new("/swagger-ui/{_:.*}")
-                    .url("/api-docs/openapi.json", ApiDoc::openapi())
+                SwaggerUi::new(format!("{}/swagger-ui/{{_:.*}}", path_prefix))
+                    .url(format!("{}/api-docs/openapi.json", path_prefix), ApiDoc::openapi())
             )
-            .configure(configure_routes)
+            .service(web::scope(&path_prefix).configure(configure_routes))
     })
-    .workers(num_cpus::get()) // Use all available CPU cores
-    .bind((config.host.as_str(), config.port as u16))?;
+    .workers(config.server.workers.unwrap_or_else(num_cpus::get)) // All CPU cores by default
+    // On SIGTERM/SIGINT stop accepting connections, then give in-flight requests this long
+    .shutdown_timeout(config.server.shutdown_timeout_secs)
+    .bind((config.server.host.as_str(), config.server.port))?;
 
-    match server.run().await {
+    let result = match server.run().await {
         Ok(_) => {
             info!("Server stopped gracefully");
             Ok(())
@@ -96,5 +219,40 @@ async fn main() -> std::io::Result<()> {
             error!("Server error: {}", e);
             Err(e)
         }
+    };
+
+    // Stop periodic jobs first, so none starts once the drain has begun
+    shutdown.cancel();
+    jobs.close();
+    if tokio::time::timeout(Duration::from_secs(config.server.drain_timeout_secs), jobs.wait())
+        .await
+        .is_err()
+    {
+        warn!("Periodic jobs still running after {}s", config.server.drain_timeout_secs);
+    }
+
+    // Flush pending click writes before closing the pool they use
+    let pending = background.len();
+    if pending > 0 {
+        info!("Waiting for {} background tasks to finish", pending);
+    }
+    let drain = background
+        .drain(Duration::from_secs(config.server.drain_timeout_secs))
+        .await;
+    if drain.abandoned.is_empty() {
+        info!("Background tasks drained ({} completed)", drain.completed);
+    } else {
+        let total: usize = drain.abandoned.values().sum();
+        warn!(
+            abandoned = ?drain.abandoned,
+            "Abandoned {} background tasks after {}s ({} completed)",
+            total, config.server.drain_timeout_secs, drain.completed
+        );
     }
+
+    // Redis connections are opened per operation, so none outlive the drained tasks
+    db_manager.close().await;
+
+    telemetry.shutdown();
+    result
 }
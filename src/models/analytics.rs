@@ -0,0 +1,96 @@
+use chrono::{DateTime, Datelike, Days, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+use super::{Browser, DeviceClass};
+
+/// Most points in one time series
+pub const MAX_TIMESERIES_POINTS: i64 = 1000;
+
+/// One human click, kept until it has been rolled up and its retention ends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClickEvent {
+    /// Host of the `Referer`, when the visitor's browser sent one
+    pub referrer: Option<String>,
+    /// ISO 3166-1 alpha-2 code, when GeoIP lookups are configured and succeed
+    pub country: Option<String>,
+    pub device: DeviceClass,
+    pub browser: Browser,
+}
+
+/// Bucket size of a click time series; hours come from the hourly rollups,
+/// days and weeks (starting Monday) from the daily ones, all in UTC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeseriesInterval {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+impl TimeseriesInterval {
+    pub fn length(self) -> Duration {
+        match self {
+            TimeseriesInterval::Hour => Duration::hours(1),
+            TimeseriesInterval::Day => Duration::days(1),
+            TimeseriesInterval::Week => Duration::weeks(1),
+        }
+    }
+
+    /// Range of a time series without `from`: a day of hours, 30 days or 12 weeks
+    pub fn default_span(self) -> Duration {
+        match self {
+            TimeseriesInterval::Hour => Duration::hours(24),
+            TimeseriesInterval::Day => Duration::days(30),
+            TimeseriesInterval::Week => Duration::weeks(12),
+        }
+    }
+
+    /// Start of the bucket `time` falls in
+    pub fn bucket_start(self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let day = time.date_naive();
+        let start = match self {
+            TimeseriesInterval::Hour => return time.duration_trunc(Duration::hours(1)).unwrap_or(time),
+            TimeseriesInterval::Day => day,
+            TimeseriesInterval::Week => day - Days::new(day.weekday().num_days_from_monday().into()),
+        };
+        start.and_time(chrono::NaiveTime::MIN).and_utc()
+    }
+}
+
+impl fmt::Display for TimeseriesInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TimeseriesInterval::Hour => "hour",
+            TimeseriesInterval::Day => "day",
+            TimeseriesInterval::Week => "week",
+        })
+    }
+}
+
+impl FromStr for TimeseriesInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hour" => Ok(TimeseriesInterval::Hour),
+            "day" => Ok(TimeseriesInterval::Day),
+            "week" => Ok(TimeseriesInterval::Week),
+            other => Err(format!("unknown interval '{}', expected hour, day or week", other)),
+        }
+    }
+}
+
+/// Outcome of rolling up raw clicks and applying the retention periods
+#[derive(Debug, Default, Serialize)]
+pub struct RollupReport {
+    /// Raw clicks added to the hourly and daily counts
+    pub rolled_up: u64,
+    /// Rolled-up raw clicks deleted after their retention period
+    pub pruned_events: u64,
+    /// Hourly counts deleted after their retention period
+    pub pruned_hours: u64,
+}
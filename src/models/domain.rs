@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// Key of the default domain, used for links not bound to a custom domain
+pub const DEFAULT_DOMAIN: &str = "";
+
+/// Custom domain served by this deployment, resolved from the `Host` header
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Domain {
+    pub id: i32,
+    /// Lowercase host name without port
+    pub host: String,
+    /// Public base URL used to build short URLs on this domain
+    pub base_url: String,
+    /// Where to send visitors of unknown short codes, instead of a 404
+    pub not_found_url: Option<String>,
+    /// Where to send visitors of the domain root, instead of a 404
+    pub root_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Domain {
+    pub fn new(host: String, base_url: String) -> Self {
+        Self {
+            id: 0,
+            host,
+            base_url,
+            not_found_url: None,
+            root_url: None,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Normalize a `Host` header value: lowercase, without port or trailing dot
+pub fn normalize_host(host: &str) -> String {
+    let host = host.trim().to_ascii_lowercase();
+
+    // Keep bracketed IPv6 literals intact, strip the port otherwise
+    let host = if host.starts_with('[') {
+        host.split(']').next().map(|h| format!("{}]", h)).unwrap_or(host)
+    } else {
+        host.split(':').next().unwrap_or_default().to_string()
+    };
+
+    host.trim_end_matches('.').to_string()
+}
@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
 use utoipa::ToSchema;
 use url::Url;
 
@@ -11,6 +13,216 @@ pub struct CreateUrlRequest {
     /// Optional custom short code
     #[schema(example = "my-custom-code")]
     pub custom_code: Option<String>,
+    /// Optional custom domain the link belongs to
+    #[schema(example = "go.example.com")]
+    pub domain: Option<String>,
+    /// Optional time after which the link stops redirecting
+    #[serde(default)]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Optional time the link goes live; visitors go to `fallback_url` before
+    #[serde(default)]
+    pub active_from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Optional time the link stops going to `url`; visitors go to `fallback_url` after
+    #[serde(default)]
+    pub active_until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Optional destination outside the activation window
+    #[schema(example = "https://www.example.com/coming-soon")]
+    #[serde(default)]
+    pub fallback_url: Option<String>,
+    /// Optional human-readable name of the link
+    #[schema(example = "Spring sale landing page")]
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Optional longer explanation of what the link is for
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Optional labels, stored lowercase
+    #[schema(example = json!(["campaign:spring", "email"]))]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Optional internal notes
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Always show the preview page instead of redirecting straight away
+    #[serde(default)]
+    pub interstitial: bool,
+    /// Optional per-platform destinations, tried in order before `url`
+    #[serde(default)]
+    pub redirect_rules: Vec<super::RedirectRule>,
+    /// Optional weighted destinations splitting visitors instead of `url`
+    #[serde(default)]
+    pub variants: Vec<super::LinkVariant>,
+    /// Optional name of the variant every visitor is sent to
+    #[serde(default)]
+    pub locked_variant: Option<String>,
+    /// Optional campaign parameters added to the destination
+    #[serde(default)]
+    pub utm: super::UtmParams,
+    /// Forward the short URL's query string and any path after the code to the destination
+    #[serde(default)]
+    pub passthrough: bool,
+    /// Treat `url` as a template whose `{name}` placeholders are filled from
+    /// path segments after the code or query parameters
+    #[serde(default)]
+    pub template: bool,
+}
+
+/// Changes to an existing link; absent fields are left as they are
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct UpdateUrlRequest {
+    /// New destination URL
+    #[schema(example = "https://www.example.com/new/path")]
+    pub url: Option<String>,
+    /// New expiry time
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Remove the expiry time, ignored when `expires_at` is set
+    #[serde(default)]
+    pub clear_expires_at: bool,
+    /// New start of the activation window
+    pub active_from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Remove the start of the activation window, ignored when `active_from` is set
+    #[serde(default)]
+    pub clear_active_from: bool,
+    /// New end of the activation window
+    pub active_until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Remove the end of the activation window, ignored when `active_until` is set
+    #[serde(default)]
+    pub clear_active_until: bool,
+    /// New destination outside the activation window, an empty string removes it
+    pub fallback_url: Option<String>,
+    /// New title, an empty string removes it
+    pub title: Option<String>,
+    /// New description, an empty string removes it
+    pub description: Option<String>,
+    /// Replacement tags, an empty list removes them
+    pub tags: Option<Vec<String>>,
+    /// New notes, an empty string removes them
+    pub notes: Option<String>,
+    /// Turn the always-preview mode on or off
+    pub interstitial: Option<bool>,
+    /// Replacement per-platform destinations, an empty list removes them
+    pub redirect_rules: Option<Vec<super::RedirectRule>>,
+    /// Replacement weighted destinations, an empty list removes them
+    pub variants: Option<Vec<super::LinkVariant>>,
+    /// Variant to send every visitor to, an empty string resumes the split
+    pub locked_variant: Option<String>,
+    /// Campaign parameters to set; absent ones are kept, empty ones removed
+    pub utm: Option<super::UtmParams>,
+    /// Turn query and path forwarding on or off
+    pub passthrough: Option<bool>,
+    /// Turn template expansion of the destination on or off
+    pub template: Option<bool>,
+}
+
+/// Query string of the link listing
+#[derive(Debug, Default, Deserialize)]
+pub struct LinkListQuery {
+    /// Only links on this domain; all domains when absent, the default one when empty
+    pub domain: Option<String>,
+    /// Comma-separated tags the links must all have
+    pub tag: Option<String>,
+    /// Text searched in code, title and destination
+    pub q: Option<String>,
+    /// Include deleted links
+    #[serde(default)]
+    pub include_deleted: bool,
+    /// Only links with a lower ID, from `next_before` of the previous page
+    pub before: Option<i32>,
+    /// Page size
+    pub limit: Option<i64>,
+}
+
+/// Normalized link listing criteria
+#[derive(Debug, Clone, Default)]
+pub struct LinkFilter {
+    pub domain: Option<String>,
+    /// Tags the links must all have
+    pub tags: Vec<String>,
+    pub search: Option<String>,
+    pub include_deleted: bool,
+    pub before: Option<i32>,
+    pub limit: i64,
+}
+
+/// One page of links, newest first
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LinkListResponse {
+    pub links: Vec<super::TinyUrl>,
+    /// `before` value of the next page, absent on the last one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_before: Option<i32>,
+}
+
+/// Outcome of rewriting the cache from the database
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CacheRebuildSummary {
+    /// Active links written to the cache
+    pub cached: u64,
+    /// Disabled or expired links removed from the cache
+    pub evicted: u64,
+}
+
+/// Link counts across all domains
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct LinkSummary {
+    pub total_links: i64,
+    /// Links that currently redirect
+    pub active_links: i64,
+    pub disabled_links: i64,
+    pub expired_links: i64,
+    /// Deleted links whose codes are still reserved
+    pub deleted_links: i64,
+    pub total_clicks: i64,
+    /// Registered custom domains
+    pub domains: i64,
+}
+
+/// Reason recorded when a link is disabled without one
+pub const DEFAULT_DISABLED_REASON: &str = "disabled by administrator";
+
+/// Request to disable a link
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct DisableUrlRequest {
+    /// Why the link is taken down, kept on the link
+    #[schema(example = "phishing report #1234")]
+    pub reason: Option<String>,
+}
+
+/// Request to register a custom domain
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateDomainRequest {
+    /// Host name matched against the `Host` header
+    #[schema(example = "go.example.com")]
+    pub host: String,
+    /// Public base URL for short URLs on this domain
+    #[schema(example = "https://go.example.com")]
+    pub base_url: String,
+    /// Optional redirect target for unknown short codes
+    #[schema(example = "https://example.com/link-not-found")]
+    pub not_found_url: Option<String>,
+    /// Optional redirect target for the domain root
+    #[schema(example = "https://example.com")]
+    pub root_url: Option<String>,
+}
+
+/// Query parameter selecting the domain of a short code
+#[derive(Debug, Deserialize)]
+pub struct DomainQuery {
+    /// Custom domain host, the default domain when absent
+    pub domain: Option<String>,
+}
+
+/// Query string of a link's click time series
+#[derive(Debug, Default, Deserialize)]
+pub struct TimeseriesQuery {
+    /// Custom domain host, the default domain when absent
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub interval: super::TimeseriesInterval,
+    /// Start of the range, rounded down to its bucket
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// End of the range, now by default
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Response when creating a shortened URL
@@ -41,20 +253,123 @@ pub struct UrlStatsResponse {
     /// Number of clicks
     #[schema(example = 42)]
     pub clicks: i32,
+    /// Approximate number of distinct visitors, to within about 1%
+    #[schema(example = 17)]
+    pub unique_visitors: u64,
+    /// Approximate distinct visitors on each of the last 7 days (UTC), newest last
+    #[schema(example = json!({"2026-10-17": 5, "2026-10-18": 3}))]
+    pub daily_unique_visitors: BTreeMap<String, u64>,
     /// Creation timestamp
     pub created_at: chrono::DateTime<chrono::Utc>,
     /// Last update timestamp
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    /// Destination page title, Open Graph tags and favicon, once fetched
+    pub page: Option<super::PageMetadata>,
+    /// Clicks by visitor country (ISO 3166-1 alpha-2), counted while GeoIP lookups are configured
+    #[schema(example = json!({"DE": 30, "US": 12}))]
+    pub countries: BTreeMap<String, i64>,
+    /// Clicks of each variant of a split link
+    pub variants: Vec<VariantStats>,
+    /// Visits by bots, crawlers and link previews, which are left out of every other count
+    #[schema(example = 9)]
+    pub bot_clicks: i64,
+    /// Bot visits by what gave them away: a `User-Agent` pattern, `head`, `prefetch` or `no-user-agent`
+    #[schema(example = json!({"slackbot": 6, "head": 3}))]
+    pub bots: BTreeMap<String, i64>,
+}
+
+/// Clicks of a link over time, from the hourly and daily rollups
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TimeseriesResponse {
+    #[schema(example = "abc123")]
+    pub short_code: String,
+    pub interval: super::TimeseriesInterval,
+    /// Start of the first bucket
+    pub from: chrono::DateTime<chrono::Utc>,
+    /// End of the range, inside the last bucket
+    pub to: chrono::DateTime<chrono::Utc>,
+    /// Every bucket in the range, oldest first, including those without clicks
+    pub points: Vec<TimeseriesPoint>,
+}
+
+/// Clicks in one bucket of a time series; visits without a referrer or a
+/// known country are left out of those breakdowns
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct TimeseriesPoint {
+    /// Start of the bucket
+    pub start: chrono::DateTime<chrono::Utc>,
+    #[schema(example = 12)]
+    pub clicks: i64,
+    /// Clicks by referring host
+    #[schema(example = json!({"news.ycombinator.com": 7, "t.co": 2}))]
+    pub referrers: BTreeMap<String, i64>,
+    /// Clicks by visitor country (ISO 3166-1 alpha-2)
+    #[schema(example = json!({"DE": 5, "US": 4}))]
+    pub countries: BTreeMap<String, i64>,
+    /// Clicks by device class: `mobile`, `tablet` or `desktop`
+    #[schema(example = json!({"desktop": 8, "mobile": 4}))]
+    pub devices: BTreeMap<String, i64>,
+    /// Clicks by browser family
+    #[schema(example = json!({"chrome": 9, "firefox": 3}))]
+    pub browsers: BTreeMap<String, i64>,
+}
+
+/// One variant of a split link and the clicks sent to it
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VariantStats {
+    #[schema(example = "B")]
+    pub name: String,
+    pub url: String,
+    pub weight: u32,
+    #[schema(example = 12)]
+    pub clicks: i64,
+    /// Every visitor is sent to this variant
+    pub locked: bool,
+}
+
+/// Custom code availability response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CodeAvailabilityResponse {
+    /// The requested code
+    #[schema(example = "my-link")]
+    pub code: String,
+    /// Whether the code can be claimed
+    pub available: bool,
+    /// Free alternatives, empty when the code is available
+    #[schema(example = json!(["my-link-1", "link-my", "mylink"]))]
+    pub suggestions: Vec<String>,
 }
 
 /// Health check response
 #[derive(Debug, Serialize, ToSchema)]
 pub struct HealthResponse {
-    /// Service status
+    /// Service status: `healthy`, `degraded` (optional dependency down) or `unhealthy`
     #[schema(example = "healthy")]
     pub status: String,
     /// Timestamp
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Dependency checks by component name, omitted by the liveness probe
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+/// Result of a single dependency check
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComponentHealth {
+    /// `up`, `down` or `disabled`
+    #[schema(example = "up")]
+    pub status: String,
+    /// Check round-trip time in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 1.7)]
+    pub latency_ms: Option<f64>,
+    /// Failure reason when down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 impl CreateUrlRequest {
@@ -65,6 +380,33 @@ impl CreateUrlRequest {
             Err(_) => return Err(super::AppError::InvalidUrl("Invalid URL format".to_string())),
         }
 
+        if self.expires_at.is_some_and(|expires_at| expires_at <= chrono::Utc::now()) {
+            return Err(super::AppError::Validation(
+                "Expiry time must be in the future".to_string()
+            ));
+        }
+        if self.active_until.is_some_and(|active_until| active_until <= chrono::Utc::now()) {
+            return Err(super::AppError::Validation(
+                "active_until must be in the future".to_string()
+            ));
+        }
+        super::validate_schedule(self.active_from, self.active_until, self.fallback_url.as_deref())
+            .map_err(super::AppError::Validation)?;
+
+        self.metadata()?;
+        super::validate_redirect_rules(&self.redirect_rules).map_err(super::AppError::Validation)?;
+        let variants = self.variants()?;
+        self.utm()?;
+        if self.template {
+            let others: Vec<&str> = self
+                .redirect_rules
+                .iter()
+                .map(|rule| rule.url.as_str())
+                .chain(variants.iter().map(|variant| variant.url.as_str()))
+                .collect();
+            super::validate_template(&self.url, &others).map_err(super::AppError::Validation)?;
+        }
+
         // Validate custom code if provided
         if let Some(code) = &self.custom_code {
             if code.is_empty() || code.len() > 20 {
@@ -73,12 +415,80 @@ impl CreateUrlRequest {
                 ));
             }
             
-            // Only allow alphanumeric characters and hyphens
-            if !code.chars().all(|c| c.is_alphanumeric() || c == '-') {
+            // Only allow alphanumeric characters, hyphens and underscores, as in generated word codes
+            if !code.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
                 return Err(super::AppError::Validation(
-                    "Custom code can only contain alphanumeric characters and hyphens".to_string()
+                    "Custom code can only contain alphanumeric characters, hyphens and underscores".to_string()
                 ));
             }
+
+            if crate::routes::is_reserved_code(code) {
+                return Err(super::AppError::Validation(format!(
+                    "Custom code '{}' is reserved for the service's own routes",
+                    code
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CreateUrlRequest {
+    /// Variants with default names filled in
+    pub fn variants(&self) -> Result<Vec<super::LinkVariant>, super::AppError> {
+        super::normalize_variants(self.variants.clone(), self.locked_variant.as_deref())
+            .map_err(super::AppError::Validation)
+    }
+
+    /// Campaign parameters with blank ones dropped
+    pub fn utm(&self) -> Result<super::UtmParams, super::AppError> {
+        self.utm.normalized().map_err(super::AppError::Validation)
+    }
+
+    /// Normalized title, description, tags and notes
+    pub fn metadata(&self) -> Result<super::LinkMetadata, super::AppError> {
+        super::LinkMetadata::new(
+            self.title.as_deref(),
+            self.description.as_deref(),
+            &self.tags,
+            self.notes.as_deref(),
+        )
+        .map_err(super::AppError::Validation)
+    }
+}
+
+impl UpdateUrlRequest {
+    pub fn validate(&self) -> Result<(), super::AppError> {
+        if let Some(url) = &self.url {
+            if Url::parse(url).is_err() {
+                return Err(super::AppError::InvalidUrl("Invalid URL format".to_string()));
+            }
+        }
+        if let Some(rules) = &self.redirect_rules {
+            super::validate_redirect_rules(rules).map_err(super::AppError::Validation)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CreateDomainRequest {
+    pub fn validate(&self) -> Result<(), super::AppError> {
+        let host = super::normalize_host(&self.host);
+        if host.is_empty() || host.len() > 255 || host.contains('/') {
+            return Err(super::AppError::Validation(
+                "Host must be a bare host name of at most 255 characters".to_string()
+            ));
+        }
+
+        for url in [Some(&self.base_url), self.not_found_url.as_ref(), self.root_url.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            if Url::parse(url).is_err() {
+                return Err(super::AppError::InvalidUrl(format!("Invalid URL format: {}", url)));
+            }
         }
 
         Ok(())
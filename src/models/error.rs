@@ -1,6 +1,5 @@
 use actix_web::{HttpResponse, ResponseError};
 use serde::{Deserialize, Serialize};
-use std::fmt;
 use thiserror::Error;
 use utoipa::ToSchema;
 
@@ -19,14 +18,26 @@ pub enum AppError {
     #[error("Invalid URL: {0}")]
     InvalidUrl(String),
     
+    #[error("URL no longer available: {0}")]
+    Gone(String),
+
     #[error("URL already exists: {0}")]
     AlreadyExists(String),
+
+    #[error("Custom code already taken: {code}")]
+    CodeTaken {
+        code: String,
+        suggestions: Vec<String>,
+    },
     
     #[error("Internal server error: {0}")]
     Internal(String),
     
     #[error("Validation error: {0}")]
     Validation(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
 }
 
 /// API error response
@@ -35,6 +46,12 @@ pub struct ErrorResponse {
     pub error: String,
     pub message: String,
     pub code: u16,
+    /// Free alternatives when a requested custom code is taken
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestions: Option<Vec<String>>,
+    /// ID of the failed request, also sent in the `X-Request-Id` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl ResponseError for AppError {
@@ -43,12 +60,20 @@ impl ResponseError for AppError {
             error: self.to_string(),
             message: match self {
                 AppError::NotFound(_) => "Resource not found".to_string(),
+                AppError::Gone(_) => "Resource was disabled or deleted".to_string(),
                 AppError::InvalidUrl(_) => "Invalid URL provided".to_string(),
                 AppError::AlreadyExists(_) => "Resource already exists".to_string(),
+                AppError::CodeTaken { .. } => "Custom code is already taken".to_string(),
                 AppError::Validation(_) => "Validation failed".to_string(),
+                AppError::Unauthorized(_) => "Authentication required".to_string(),
                 _ => "Internal server error".to_string(),
             },
             code: self.status_code().as_u16(),
+            suggestions: match self {
+                AppError::CodeTaken { suggestions, .. } => Some(suggestions.clone()),
+                _ => None,
+            },
+            request_id: crate::core::request_id::current(),
         };
 
         HttpResponse::build(self.status_code()).json(error_response)
@@ -57,9 +82,12 @@ impl ResponseError for AppError {
     fn status_code(&self) -> actix_web::http::StatusCode {
         match self {
             AppError::NotFound(_) => actix_web::http::StatusCode::NOT_FOUND,
+            AppError::Gone(_) => actix_web::http::StatusCode::GONE,
             AppError::InvalidUrl(_) => actix_web::http::StatusCode::BAD_REQUEST,
             AppError::AlreadyExists(_) => actix_web::http::StatusCode::CONFLICT,
+            AppError::CodeTaken { .. } => actix_web::http::StatusCode::CONFLICT,
             AppError::Validation(_) => actix_web::http::StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => actix_web::http::StatusCode::UNAUTHORIZED,
             _ => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
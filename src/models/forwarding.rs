@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+use utoipa::ToSchema;
+
+/// Longest UTM parameter value, in characters
+pub const MAX_UTM_LENGTH: usize = 200;
+
+/// Campaign parameters added to a link's destination as `utm_*` query parameters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct UtmParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "newsletter")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "email")]
+    pub medium: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "spring-sale")]
+    pub campaign: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub term: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+impl UtmParams {
+    pub fn is_empty(&self) -> bool {
+        self.pairs().is_empty()
+    }
+
+    /// Trimmed values with empty ones removed, describing the first problem found
+    pub fn normalized(&self) -> Result<Self, String> {
+        let clean = |name: &str, value: &Option<String>| -> Result<Option<String>, String> {
+            let Some(value) = value.as_deref().map(str::trim).filter(|value| !value.is_empty()) else {
+                return Ok(None);
+            };
+            if value.chars().count() > MAX_UTM_LENGTH {
+                return Err(format!("utm {} can be at most {} characters", name, MAX_UTM_LENGTH));
+            }
+            Ok(Some(value.to_string()))
+        };
+
+        Ok(Self {
+            source: clean("source", &self.source)?,
+            medium: clean("medium", &self.medium)?,
+            campaign: clean("campaign", &self.campaign)?,
+            term: clean("term", &self.term)?,
+            content: clean("content", &self.content)?,
+        })
+    }
+
+    /// These parameters laid over `base`: given fields replace its values and
+    /// empty ones remove them
+    pub fn merged_into(&self, base: &UtmParams) -> Result<Self, String> {
+        let pick = |field: &Option<String>, base: &Option<String>| field.clone().or_else(|| base.clone());
+        Self {
+            source: pick(&self.source, &base.source),
+            medium: pick(&self.medium, &base.medium),
+            campaign: pick(&self.campaign, &base.campaign),
+            term: pick(&self.term, &base.term),
+            content: pick(&self.content, &base.content),
+        }
+        .normalized()
+    }
+
+    /// Set parameters as query parameter names and values
+    pub fn pairs(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("utm_source", &self.source),
+            ("utm_medium", &self.medium),
+            ("utm_campaign", &self.campaign),
+            ("utm_term", &self.term),
+            ("utm_content", &self.content),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.as_deref().map(|value| (name, value)))
+        .collect()
+    }
+}
+
+/// Parts of a short URL request forwarded to the destination of passthrough links
+#[derive(Debug, Clone, Default)]
+pub struct Forwarded {
+    /// Raw query string, without the `?`
+    pub query: Option<String>,
+    /// Raw path after the short code, without the leading `/`
+    pub path_suffix: Option<String>,
+}
+
+impl Forwarded {
+    /// Whether the path suffix stays below the destination path, i.e. has no
+    /// `.` or `..` segments, even percent-encoded
+    pub fn has_safe_path(&self) -> bool {
+        self.path_suffix.as_deref().is_none_or(|suffix| {
+            suffix.split('/').all(|segment| {
+                let segment = segment.to_ascii_lowercase().replace("%2e", ".");
+                segment != "." && segment != ".."
+            })
+        })
+    }
+}
+
+/// Destination URL of a visit with the UTM parameters and forwarded request parts
+/// merged in
+///
+/// UTM parameters replace same-named parameters of `location`. The path suffix
+/// is appended to the destination path. Forwarded query parameters are added
+/// after the destination's own, except those the destination or the UTM
+/// parameters already set, which cannot be overridden by visitors. The
+/// destination's own query is kept as written; added parameters are encoded.
+pub fn forward_location(location: &str, utm: &UtmParams, forwarded: Option<&Forwarded>) -> String {
+    let utm_pairs = utm.pairs();
+    let forwarded_query = forwarded
+        .and_then(|forwarded| forwarded.query.as_deref())
+        .filter(|query| !query.is_empty());
+    let path_suffix = forwarded
+        .and_then(|forwarded| forwarded.path_suffix.as_deref())
+        .filter(|suffix| !suffix.is_empty());
+    if utm_pairs.is_empty() && forwarded_query.is_none() && path_suffix.is_none() {
+        return location.to_string();
+    }
+    let Ok(mut url) = Url::parse(location) else {
+        return location.to_string();
+    };
+
+    if let Some(suffix) = path_suffix {
+        let path = format!("{}/{}", url.path().trim_end_matches('/'), suffix);
+        url.set_path(&path);
+    }
+
+    // Keep the destination's parameters as written, minus those UTM replaces
+    let mut taken: Vec<String> = Vec::new();
+    let mut segments: Vec<String> = Vec::new();
+    for segment in url.query().unwrap_or_default().split('&').filter(|s| !s.is_empty()) {
+        let name = form_urlencoded::parse(segment.as_bytes())
+            .next()
+            .map(|(name, _)| name.into_owned())
+            .unwrap_or_default();
+        if utm_pairs.iter().any(|(utm_name, _)| *utm_name == name) {
+            continue;
+        }
+        taken.push(name);
+        segments.push(segment.to_string());
+    }
+
+    let mut added = form_urlencoded::Serializer::new(String::new());
+    for (name, value) in &utm_pairs {
+        added.append_pair(name, value);
+        taken.push(name.to_string());
+    }
+    if let Some(query) = forwarded_query {
+        for (name, value) in form_urlencoded::parse(query.as_bytes()) {
+            if !taken.iter().any(|taken| *taken == name) {
+                added.append_pair(&name, &value);
+            }
+        }
+    }
+    let added = added.finish();
+    if !added.is_empty() {
+        segments.push(added);
+    }
+
+    let query = segments.join("&");
+    url.set_query(Some(query.as_str()).filter(|query| !query.is_empty()));
+    url.to_string()
+}
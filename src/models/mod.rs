@@ -1,7 +1,19 @@
 pub mod url;
 pub mod error;
 pub mod dto;
+pub mod domain;
+pub mod transfer;
+pub mod targeting;
+pub mod forwarding;
+pub mod template;
+pub mod analytics;
 
 pub use url::*;
 pub use error::*;
-pub use dto::*; 
\ No newline at end of file
+pub use dto::*;
+pub use domain::*;
+pub use transfer::*;
+pub use targeting::*;
+pub use forwarding::*;
+pub use template::*;
+pub use analytics::*; 
\ No newline at end of file
@@ -0,0 +1,452 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+use utoipa::ToSchema;
+
+use super::Forwarded;
+
+/// Most redirect rules on one link
+pub const MAX_REDIRECT_RULES: usize = 20;
+
+/// Operating system of a visitor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Os {
+    Ios,
+    Android,
+    Windows,
+    Macos,
+    Linux,
+    Chromeos,
+    #[default]
+    Other,
+}
+
+/// Form factor of a visitor's device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceClass {
+    Mobile,
+    Tablet,
+    #[default]
+    Desktop,
+}
+
+/// Browser family of a visitor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Browser {
+    Chrome,
+    Safari,
+    Firefox,
+    Edge,
+    Opera,
+    Samsung,
+    #[default]
+    Other,
+}
+
+impl DeviceClass {
+    /// Name as serialized
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeviceClass::Mobile => "mobile",
+            DeviceClass::Tablet => "tablet",
+            DeviceClass::Desktop => "desktop",
+        }
+    }
+}
+
+impl Browser {
+    /// Name as serialized
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Browser::Chrome => "chrome",
+            Browser::Safari => "safari",
+            Browser::Firefox => "firefox",
+            Browser::Edge => "edge",
+            Browser::Opera => "opera",
+            Browser::Samsung => "samsung",
+            Browser::Other => "other",
+        }
+    }
+}
+
+/// Platform of a visitor as told by its `User-Agent`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ClientDevice {
+    pub os: Os,
+    pub device: DeviceClass,
+    pub browser: Browser,
+}
+
+impl ClientDevice {
+    /// Classify a `User-Agent` header; unknown agents count as desktop
+    pub fn from_user_agent(user_agent: &str) -> Self {
+        let has = |token: &str| user_agent.contains(token);
+
+        let os = if has("iPhone") || has("iPad") || has("iPod") {
+            Os::Ios
+        } else if has("Android") {
+            Os::Android
+        } else if has("Windows") {
+            Os::Windows
+        } else if has("CrOS") {
+            Os::Chromeos
+        } else if has("Macintosh") || has("Mac OS X") {
+            Os::Macos
+        } else if has("Linux") {
+            Os::Linux
+        } else {
+            Os::Other
+        };
+
+        // Android tablets leave "Mobile" out of their agent
+        let device = if has("iPad") || has("Tablet") || (os == Os::Android && !has("Mobile")) {
+            DeviceClass::Tablet
+        } else if has("Mobi") || has("iPhone") || has("iPod") {
+            DeviceClass::Mobile
+        } else {
+            DeviceClass::Desktop
+        };
+
+        // Most agents also claim Chrome and Safari, so the specific ones go first
+        let browser = if has("Edg/") || has("EdgA/") || has("EdgiOS/") {
+            Browser::Edge
+        } else if has("OPR/") || has("Opera") {
+            Browser::Opera
+        } else if has("SamsungBrowser/") {
+            Browser::Samsung
+        } else if has("Firefox/") || has("FxiOS/") {
+            Browser::Firefox
+        } else if has("Chrome/") || has("CriOS/") || has("Chromium/") {
+            Browser::Chrome
+        } else if has("Safari/") {
+            Browser::Safari
+        } else {
+            Browser::Other
+        };
+
+        Self { os, device, browser }
+    }
+}
+
+/// What is known about the visitor of a short link, for choosing its destination
+#[derive(Debug, Clone, Default)]
+pub struct VisitContext {
+    pub device: ClientDevice,
+    /// ISO 3166-1 alpha-2 country code, when GeoIP lookups are configured and succeed
+    pub country: Option<String>,
+    /// Stable random ID of the visitor's browser, for sticky variant assignment
+    pub visitor_id: Option<String>,
+    /// Query and path of the request, forwarded by passthrough links
+    pub forwarded: Forwarded,
+    /// Hash identifying the visitor for unique visitor counts
+    pub visitor_key: Option<String>,
+    /// What marked the request as automated, e.g. `slackbot`; bots are
+    /// redirected but not counted as clicks
+    pub bot: Option<String>,
+    /// Host of the page the visitor came from
+    pub referrer: Option<String>,
+}
+
+impl VisitContext {
+    pub fn from_user_agent(user_agent: Option<&str>) -> Self {
+        Self {
+            device: user_agent.map(ClientDevice::from_user_agent).unwrap_or_default(),
+            country: None,
+            visitor_id: None,
+            forwarded: Forwarded::default(),
+            visitor_key: None,
+            bot: None,
+            referrer: None,
+        }
+    }
+
+    pub fn with_country(mut self, country: Option<String>) -> Self {
+        self.country = country;
+        self
+    }
+
+    pub fn with_visitor_id(mut self, visitor_id: Option<String>) -> Self {
+        self.visitor_id = visitor_id;
+        self
+    }
+
+    pub fn with_forwarded(mut self, forwarded: Forwarded) -> Self {
+        self.forwarded = forwarded;
+        self
+    }
+
+    pub fn with_bot(mut self, bot: Option<String>) -> Self {
+        self.bot = bot;
+        self
+    }
+
+    pub fn with_referrer(mut self, referrer: Option<String>) -> Self {
+        self.referrer = referrer;
+        self
+    }
+
+    /// Count the visitor as `identity`, which is only kept hashed
+    pub fn with_visitor_identity(mut self, identity: &str) -> Self {
+        self.visitor_key = Some(format!("{:x}", Sha256::digest(identity.as_bytes())));
+        self
+    }
+}
+
+/// Destination for visitors matching every given condition; an empty
+/// condition matches anyone
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RedirectRule {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<Os>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub device: Vec<DeviceClass>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub browser: Vec<Browser>,
+    /// ISO 3166-1 alpha-2 country codes; visitors whose country is unknown never match
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(example = json!(["DE", "AT", "CH"]))]
+    pub country: Vec<String>,
+    /// Where matching visitors are sent
+    #[schema(example = "https://apps.apple.com/app/id123456789")]
+    pub url: String,
+}
+
+impl RedirectRule {
+    pub fn matches(&self, visit: &VisitContext) -> bool {
+        let device = &visit.device;
+        (self.os.is_empty() || self.os.contains(&device.os))
+            && (self.device.is_empty() || self.device.contains(&device.device))
+            && (self.browser.is_empty() || self.browser.contains(&device.browser))
+            && (self.country.is_empty()
+                || visit.country.as_ref().is_some_and(|country| {
+                    self.country.iter().any(|c| c.eq_ignore_ascii_case(country))
+                }))
+    }
+
+    /// Whether the rule looks at the visitor's `User-Agent`
+    pub fn uses_device(&self) -> bool {
+        !(self.os.is_empty() && self.device.is_empty() && self.browser.is_empty())
+    }
+
+    /// Whether the rule looks at the visitor's country
+    pub fn uses_country(&self) -> bool {
+        !self.country.is_empty()
+    }
+}
+
+/// Check a link's rule list, describing the first problem found
+pub fn validate_redirect_rules(rules: &[RedirectRule]) -> Result<(), String> {
+    if rules.len() > MAX_REDIRECT_RULES {
+        return Err(format!("a link can have at most {} redirect rules", MAX_REDIRECT_RULES));
+    }
+    for (i, rule) in rules.iter().enumerate() {
+        if !is_web_url(&rule.url) {
+            return Err(format!("redirect rule {} has an invalid url '{}'", i + 1, rule.url));
+        }
+        if let Some(country) = rule
+            .country
+            .iter()
+            .find(|country| country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()))
+        {
+            return Err(format!(
+                "redirect rule {} has an invalid country '{}', expected a two-letter ISO code",
+                i + 1,
+                country
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `url` parses as an HTTP(S) URL, the only destinations sent to visitors
+fn is_web_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Most variants on one link
+pub const MAX_VARIANTS: usize = 10;
+/// Longest variant name, in characters
+pub const MAX_VARIANT_NAME_LENGTH: usize = 50;
+/// Largest weight of one variant
+pub const MAX_VARIANT_WEIGHT: u32 = 10_000;
+
+/// One destination of a split link and its share of visitors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct LinkVariant {
+    /// Label in statistics and for locking; `A`, `B`, ... by position when omitted
+    #[serde(default)]
+    #[schema(example = "B")]
+    pub name: String,
+    #[schema(example = "https://example.com/landing-b")]
+    pub url: String,
+    /// Share of visitors relative to the other variants' weights; 0 pauses the variant
+    #[serde(default = "default_variant_weight")]
+    #[schema(example = 30)]
+    pub weight: u32,
+}
+
+fn default_variant_weight() -> u32 {
+    1
+}
+
+/// Name variants left unnamed and check the list and the locked variant,
+/// describing the first problem found
+pub fn normalize_variants(
+    mut variants: Vec<LinkVariant>,
+    locked: Option<&str>,
+) -> Result<Vec<LinkVariant>, String> {
+    if variants.len() > MAX_VARIANTS {
+        return Err(format!("a link can have at most {} variants", MAX_VARIANTS));
+    }
+    for (i, variant) in variants.iter_mut().enumerate() {
+        variant.name = variant.name.trim().to_string();
+        if variant.name.is_empty() {
+            variant.name = char::from(b'A' + i as u8).to_string();
+        }
+    }
+
+    for (i, variant) in variants.iter().enumerate() {
+        if variant.name.chars().count() > MAX_VARIANT_NAME_LENGTH {
+            return Err(format!(
+                "variant names can be at most {} characters",
+                MAX_VARIANT_NAME_LENGTH
+            ));
+        }
+        if variants[..i].iter().any(|other| other.name == variant.name) {
+            return Err(format!("variant name '{}' is used twice", variant.name));
+        }
+        if !is_web_url(&variant.url) {
+            return Err(format!("variant '{}' has an invalid url '{}'", variant.name, variant.url));
+        }
+        if variant.weight > MAX_VARIANT_WEIGHT {
+            return Err(format!("variant weights can be at most {}", MAX_VARIANT_WEIGHT));
+        }
+    }
+    if !variants.is_empty() && variants.iter().all(|variant| variant.weight == 0) {
+        return Err("at least one variant needs a weight above 0".to_string());
+    }
+
+    if let Some(locked) = locked {
+        if !variants.iter().any(|variant| variant.name == locked) {
+            return Err(format!("locked variant '{}' is not one of the link's variants", locked));
+        }
+    }
+    Ok(variants)
+}
+
+/// Variant a visitor is assigned to: a point in the total weight picked by
+/// hashing the visitor ID with the link, so the same visitor keeps getting the
+/// same variant while the weights stay the same
+pub fn assign_variant<'a>(
+    variants: &'a [LinkVariant],
+    visitor_id: &str,
+    link: &str,
+) -> Option<&'a LinkVariant> {
+    let total: u64 = variants.iter().map(|variant| u64::from(variant.weight)).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let digest = Sha256::new()
+        .chain_update(visitor_id.as_bytes())
+        .chain_update([0])
+        .chain_update(link.as_bytes())
+        .finalize();
+    let mut point = u64::from_be_bytes(digest[..8].try_into().ok()?) % total;
+    variants.iter().find(|variant| {
+        let weight = u64::from(variant.weight);
+        if point < weight {
+            return true;
+        }
+        point -= weight;
+        false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(country: &[&str]) -> RedirectRule {
+        RedirectRule {
+            os: Vec::new(),
+            device: Vec::new(),
+            browser: Vec::new(),
+            country: country.iter().map(|country| country.to_string()).collect(),
+            url: "https://example.com/local".to_string(),
+        }
+    }
+
+    fn visit_from(country: Option<&str>) -> VisitContext {
+        VisitContext::default().with_country(country.map(str::to_string))
+    }
+
+    #[test]
+    fn country_rules_match_listed_countries_ignoring_case() {
+        let rule = rule(&["DE", "at"]);
+        assert!(rule.matches(&visit_from(Some("DE"))));
+        assert!(rule.matches(&visit_from(Some("AT"))));
+        assert!(rule.matches(&visit_from(Some("de"))));
+        assert!(!rule.matches(&visit_from(Some("US"))));
+    }
+
+    #[test]
+    fn country_rules_never_match_unknown_countries() {
+        assert!(!rule(&["DE"]).matches(&visit_from(None)));
+        assert!(rule(&[]).matches(&visit_from(None)));
+    }
+
+    #[test]
+    fn country_and_device_conditions_must_both_match() {
+        let rule = RedirectRule {
+            device: vec![DeviceClass::Mobile],
+            ..rule(&["DE"])
+        };
+        let iphone = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) Mobile/15E148 Safari/604.1";
+        let mobile_in = |country: &str| {
+            VisitContext::from_user_agent(Some(iphone)).with_country(Some(country.to_string()))
+        };
+
+        assert!(rule.matches(&mobile_in("DE")));
+        assert!(!rule.matches(&mobile_in("FR")));
+        assert!(!rule.matches(&visit_from(Some("DE"))));
+        assert!(rule.uses_country() && rule.uses_device());
+    }
+
+    #[test]
+    fn validation_rejects_non_web_rule_urls() {
+        for url in ["javascript:alert(document.cookie)", "data:text/html,hi", "file:///etc/passwd"] {
+            let rule = RedirectRule { url: url.to_string(), ..rule(&[]) };
+            assert!(validate_redirect_rules(&[rule]).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn validation_rejects_malformed_countries() {
+        assert!(validate_redirect_rules(&[rule(&["DE"])]).is_ok());
+        assert!(validate_redirect_rules(&[rule(&["DEU"])]).is_err());
+        assert!(validate_redirect_rules(&[rule(&["D1"])]).is_err());
+    }
+
+    fn variant(name: &str, url: &str) -> LinkVariant {
+        LinkVariant {
+            name: name.to_string(),
+            url: url.to_string(),
+            weight: 1,
+        }
+    }
+
+    #[test]
+    fn variants_need_web_urls() {
+        let web = vec![variant("A", "https://example.com/a"), variant("B", "http://example.com/b")];
+        assert!(normalize_variants(web, None).is_ok());
+
+        for url in ["javascript:alert(1)", "data:text/html,hi", "file:///etc/passwd", "ftp://example.com/"] {
+            let variants = vec![variant("A", "https://example.com/a"), variant("B", url)];
+            assert!(normalize_variants(variants, None).is_err(), "{}", url);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::HashMap;
+use url::{form_urlencoded, Url};
+
+use super::Forwarded;
+
+/// Most placeholders in one template
+pub const MAX_PLACEHOLDERS: usize = 10;
+/// Longest placeholder name
+pub const MAX_PLACEHOLDER_NAME_LENGTH: usize = 32;
+/// Longest value filled into a placeholder, in characters
+pub const MAX_PLACEHOLDER_VALUE_LENGTH: usize = 200;
+
+/// Characters kept as they are in filled-in values; everything else is
+/// percent-encoded, so a value cannot add path segments, parameters or a fragment
+const VALUE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// Names of the `{name}` placeholders in a template, in order of first appearance
+pub fn template_placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        if !names.contains(&&after[..end]) {
+            names.push(&after[..end]);
+        }
+        rest = &after[end + 1..];
+    }
+    names
+}
+
+/// Check the URL of a template link and the other destinations it may send
+/// visitors to, describing the first problem found
+///
+/// Placeholders have to be in the path, query or fragment, so every expansion
+/// stays on the template's host, and `others` can only use the template's own.
+pub fn validate_template(template: &str, others: &[&str]) -> Result<(), String> {
+    let names = template_placeholders(template);
+    if names.is_empty() {
+        return Err("a template link needs at least one {name} placeholder in its URL".to_string());
+    }
+    if names.len() > MAX_PLACEHOLDERS {
+        return Err(format!("a template can have at most {} placeholders", MAX_PLACEHOLDERS));
+    }
+    for name in &names {
+        if name.is_empty()
+            || name.len() > MAX_PLACEHOLDER_NAME_LENGTH
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(format!(
+                "placeholder {{{}}} must be at most {} letters, digits or '_'",
+                name, MAX_PLACEHOLDER_NAME_LENGTH
+            ));
+        }
+    }
+
+    let samples: HashMap<String, String> =
+        names.iter().map(|name| (name.to_string(), "x".to_string())).collect();
+    for url in std::iter::once(&template).chain(others) {
+        if let Some(name) = template_placeholders(url).into_iter().find(|name| !names.contains(name)) {
+            return Err(format!("'{}' uses {{{}}}, which the link's URL does not have", url, name));
+        }
+        let authority = url
+            .split_once("://")
+            .map(|(_, rest)| rest.split(['/', '?', '#']).next().unwrap_or_default())
+            .unwrap_or_default();
+        if authority.contains('{') {
+            return Err(format!(
+                "'{}' has a placeholder in its host; placeholders can only be in the path, query or fragment",
+                url
+            ));
+        }
+        if expand_template(url, &samples).is_none() {
+            return Err(format!("'{}' does not expand to an http or https URL", url));
+        }
+    }
+    Ok(())
+}
+
+/// Values for a template's placeholders from a visit: the leading path
+/// segments fill them in order, query parameters of the same name fill the
+/// rest. Returns them with the path segments and parameters left over, or
+/// `None` when a placeholder gets no value or an unsafe one.
+pub fn fill_template(
+    template: &str,
+    forwarded: &Forwarded,
+) -> Option<(HashMap<String, String>, Forwarded)> {
+    let names = template_placeholders(template);
+    let mut values = HashMap::new();
+
+    let mut segments = forwarded
+        .path_suffix
+        .as_deref()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.is_empty());
+    for name in &names {
+        let Some(segment) = segments.next() else {
+            break;
+        };
+        let value = percent_decode_str(segment).decode_utf8().ok()?;
+        values.insert(name.to_string(), value.into_owned());
+    }
+    let path_suffix = Some(segments.collect::<Vec<_>>().join("/")).filter(|rest| !rest.is_empty());
+
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    for (name, value) in form_urlencoded::parse(forwarded.query.as_deref().unwrap_or_default().as_bytes()) {
+        if names.contains(&name.as_ref()) && !values.contains_key(name.as_ref()) {
+            values.insert(name.into_owned(), value.into_owned());
+        } else {
+            query.append_pair(&name, &value);
+        }
+    }
+    let query = Some(query.finish()).filter(|query| !query.is_empty());
+
+    let safe = |value: &String| {
+        !value.is_empty()
+            && value.chars().count() <= MAX_PLACEHOLDER_VALUE_LENGTH
+            && value != "."
+            && value != ".."
+    };
+    if names.iter().any(|name| !values.get(*name).is_some_and(safe)) {
+        return None;
+    }
+    Some((values, Forwarded { query, path_suffix }))
+}
+
+/// Template with its placeholders replaced by the percent-encoded `values`,
+/// or `None` when one is missing or the result leaves the template's origin
+pub fn expand_template(template: &str, values: &HashMap<String, String>) -> Option<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        let value = values.get(&after[..end])?;
+        expanded.push_str(&rest[..start]);
+        expanded.extend(utf8_percent_encode(value, VALUE));
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    let expanded = Url::parse(&expanded).ok()?;
+    let origin = Url::parse(template).ok()?.origin();
+    (matches!(expanded.scheme(), "http" | "https") && expanded.origin() == origin)
+        .then(|| expanded.to_string())
+}
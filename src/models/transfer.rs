@@ -0,0 +1,386 @@
+use super::{
+    normalize_host, normalize_variants, validate_redirect_rules, LinkMetadata, LinkVariant, TinyUrl,
+    UtmParams, validate_schedule,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use url::Url;
+use utoipa::ToSchema;
+
+/// Problems listed in an import report; the rest are only counted
+pub const MAX_IMPORT_ISSUES: usize = 100;
+
+/// Longest short code the `tinyurls.short_code` column holds
+const MAX_IMPORTED_CODE_LENGTH: usize = 20;
+
+/// Bulk import and export file format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferFormat {
+    /// Comma-separated values with a header row naming the columns
+    #[default]
+    Csv,
+    /// One JSON object per line
+    #[serde(alias = "ndjson")]
+    Jsonl,
+}
+
+impl TransferFormat {
+    /// Format implied by a file name's extension, if recognized
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(TransferFormat::Csv),
+            "jsonl" | "ndjson" => Some(TransferFormat::Jsonl),
+            _ => None,
+        }
+    }
+
+    /// HTTP content type of exported data
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TransferFormat::Csv => "text/csv; charset=utf-8",
+            TransferFormat::Jsonl => "application/x-ndjson",
+        }
+    }
+}
+
+impl FromStr for TransferFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(TransferFormat::Csv),
+            "jsonl" | "ndjson" => Ok(TransferFormat::Jsonl),
+            _ => Err("expected 'csv' or 'jsonl'".to_string()),
+        }
+    }
+}
+
+/// What to do with imported codes that already exist
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Keep the existing link and ignore the imported row
+    Skip,
+    /// Replace the existing link with the imported row
+    Overwrite,
+    /// Import nothing if any code already exists
+    #[default]
+    Fail,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "fail" => Ok(ConflictPolicy::Fail),
+            _ => Err("expected 'skip', 'overwrite' or 'fail'".to_string()),
+        }
+    }
+}
+
+/// Bulk import settings, also the query string of the admin import endpoint
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub format: TransferFormat,
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+    /// Validate and report without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Query string of the admin export endpoint
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ExportOptions {
+    #[serde(default)]
+    pub format: TransferFormat,
+}
+
+/// One link as it appears in import and export files
+///
+/// Timestamps are RFC 3339; `YYYY-MM-DD HH:MM:SS` is also accepted as UTC.
+#[derive(Debug, Deserialize)]
+pub struct LinkRecord {
+    #[serde(default)]
+    pub domain: Option<String>,
+    pub short_code: String,
+    pub long_url: String,
+    #[serde(default)]
+    pub clicks: Option<i64>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub active_from: Option<String>,
+    #[serde(default)]
+    pub active_until: Option<String>,
+    #[serde(default)]
+    pub fallback_url: Option<String>,
+    #[serde(default)]
+    pub disabled_reason: Option<String>,
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// A JSON array, or comma-separated in CSV
+    #[serde(default, deserialize_with = "deserialize_tags")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub interstitial: Option<bool>,
+    /// A JSON array, or in CSV the same array as JSON text
+    #[serde(default)]
+    pub redirect_rules: Option<serde_json::Value>,
+    /// A JSON array, or in CSV the same array as JSON text
+    #[serde(default)]
+    pub variants: Option<serde_json::Value>,
+    #[serde(default)]
+    pub locked_variant: Option<String>,
+    /// A JSON object, or in CSV the same object as JSON text
+    #[serde(default)]
+    pub utm: Option<serde_json::Value>,
+    #[serde(default)]
+    pub passthrough: Option<bool>,
+    #[serde(default)]
+    pub template: Option<bool>,
+}
+
+impl LinkRecord {
+    /// Validate the record and convert it to a link, describing the first problem found
+    pub fn into_url(self, now: DateTime<Utc>) -> Result<TinyUrl, String> {
+        let short_code = self.short_code.trim().to_string();
+        if short_code.is_empty() || short_code.len() > MAX_IMPORTED_CODE_LENGTH {
+            return Err(format!(
+                "short_code must be between 1 and {} characters",
+                MAX_IMPORTED_CODE_LENGTH
+            ));
+        }
+        if !short_code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err("short_code can only contain letters, digits, '-' and '_'".to_string());
+        }
+        if crate::routes::is_reserved_code(&short_code) {
+            return Err(format!("short_code '{}' is reserved for the service's own routes", short_code));
+        }
+
+        let long_url = self.long_url.trim().to_string();
+        match Url::parse(&long_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => return Err(format!("invalid long_url '{}'", long_url)),
+        }
+
+        let clicks = match self.clicks {
+            Some(clicks) => i32::try_from(clicks)
+                .ok()
+                .filter(|clicks| *clicks >= 0)
+                .ok_or_else(|| format!("clicks out of range: {}", clicks))?,
+            None => 0,
+        };
+
+        let created_at = parse_timestamp("created_at", self.created_at.as_deref())?.unwrap_or(now);
+        let updated_at =
+            parse_timestamp("updated_at", self.updated_at.as_deref())?.unwrap_or(created_at);
+
+        let mut url = TinyUrl::new(short_code, long_url);
+        url.domain = self.domain.as_deref().map(normalize_host).unwrap_or_default();
+        url.clicks = clicks;
+        url.created_at = created_at;
+        url.updated_at = updated_at;
+        url.expires_at = parse_timestamp("expires_at", self.expires_at.as_deref())?;
+        url.active_from = parse_timestamp("active_from", self.active_from.as_deref())?;
+        url.active_until = parse_timestamp("active_until", self.active_until.as_deref())?;
+        url.fallback_url = self
+            .fallback_url
+            .map(|fallback_url| fallback_url.trim().to_string())
+            .filter(|fallback_url| !fallback_url.is_empty());
+        validate_schedule(url.active_from, url.active_until, url.fallback_url.as_deref())?;
+        url.disabled_reason = self.disabled_reason.filter(|reason| !reason.trim().is_empty());
+        url.deleted_at = parse_timestamp("deleted_at", self.deleted_at.as_deref())?;
+        url.set_metadata(LinkMetadata::new(
+            self.title.as_deref(),
+            self.description.as_deref(),
+            &self.tags,
+            self.notes.as_deref(),
+        )?);
+        url.interstitial = self.interstitial.unwrap_or_default();
+        url.redirect_rules.0 = parse_json_field("redirect_rules", self.redirect_rules)?;
+        validate_redirect_rules(&url.redirect_rules)?;
+        url.locked_variant = self.locked_variant.filter(|locked| !locked.trim().is_empty());
+        url.variants.0 = normalize_variants(
+            parse_json_field::<Vec<LinkVariant>>("variants", self.variants)?,
+            url.locked_variant.as_deref(),
+        )?;
+        url.utm.0 = parse_json_field::<UtmParams>("utm", self.utm)?.normalized()?;
+        url.passthrough = self.passthrough.unwrap_or_default();
+        url.template = self.template.unwrap_or_default();
+        url.check_template()?;
+        Ok(url)
+    }
+}
+
+/// A list or object from JSON, or in CSV from the same JSON as text
+fn parse_json_field<T: serde::de::DeserializeOwned + Default>(
+    field: &str,
+    value: Option<serde_json::Value>,
+) -> Result<T, String> {
+    match value {
+        None | Some(serde_json::Value::Null) => Ok(T::default()),
+        Some(serde_json::Value::String(text)) if text.trim().is_empty() => Ok(T::default()),
+        Some(serde_json::Value::String(text)) => serde_json::from_str(&text),
+        Some(value) => serde_json::from_value(value),
+    }
+    .map_err(|e| format!("invalid {}: {}", field, e))
+}
+
+/// Tags from a JSON array or null, or from a comma-separated string. CSV
+/// fields that look numeric arrive as numbers, so those are accepted as text.
+fn deserialize_tags<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    struct TagsVisitor;
+
+    impl<'de> Visitor<'de> for TagsVisitor {
+        type Value = Vec<String>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a list of tags or a comma-separated string")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            Ok(value
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect())
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+            Ok(vec![value.to_string()])
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+            Ok(vec![value.to_string()])
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+            Ok(vec![value.to_string()])
+        }
+
+        fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+            Ok(vec![value.to_string()])
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut tags = Vec::new();
+            while let Some(tag) = seq.next_element::<String>()? {
+                tags.push(tag);
+            }
+            Ok(tags)
+        }
+    }
+
+    deserializer.deserialize_any(TagsVisitor)
+}
+
+fn parse_timestamp(field: &str, value: Option<&str>) -> Result<Option<DateTime<Utc>>, String> {
+    let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(None);
+    };
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|parsed| parsed.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").map(|naive| naive.and_utc())
+        })
+        .map(Some)
+        .map_err(|_| format!("invalid {} '{}'", field, value))
+}
+
+/// A validated link with its position in the input
+#[derive(Debug)]
+pub struct ImportRow {
+    pub line: u64,
+    pub url: TinyUrl,
+}
+
+/// Message from the import parser to the repository
+#[derive(Debug)]
+pub enum ImportBatch {
+    Rows(Vec<ImportRow>),
+    /// The whole input was read; without it the import is rolled back
+    End,
+}
+
+/// What the repository found and did with the staged rows
+#[derive(Debug, Default)]
+pub struct ImportOutcome {
+    pub staged: u64,
+    pub duplicates: u64,
+    pub unknown_domains: u64,
+    pub conflicts: u64,
+    pub inserted: u64,
+    pub updated: u64,
+    pub skipped: u64,
+    /// A dry run with `fail` found existing codes, so the real import would fail
+    pub failed: bool,
+    /// First problems found, by input line
+    pub issues: Vec<ImportIssue>,
+    /// Links replaced by `overwrite`, whose cached destinations are stale
+    pub overwritten: Vec<(String, String)>,
+}
+
+/// A row that was not imported as is
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImportIssue {
+    /// Line of the input the row starts on
+    pub line: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_code: Option<String>,
+    pub message: String,
+}
+
+/// Result of a bulk import, or of what it would do on a dry run
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub on_conflict: ConflictPolicy,
+    /// Data rows read from the input
+    pub rows_read: u64,
+    /// Rows rejected as malformed or referencing an unregistered domain
+    pub invalid: u64,
+    /// Rows repeating an earlier code in the input; the first one wins
+    pub duplicates: u64,
+    /// Rows whose code already exists
+    pub conflicts: u64,
+    pub inserted: u64,
+    pub updated: u64,
+    pub skipped: u64,
+    /// Whether the import as a whole would fail: a dry run with `fail` found
+    /// existing codes, listed in `issues`, and nothing would be imported
+    pub failed: bool,
+    /// First problems found, by input line
+    pub issues: Vec<ImportIssue>,
+}
@@ -1,18 +1,400 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::FromRow;
 use utoipa::ToSchema;
 
+use std::collections::HashMap;
+
+use super::{
+    assign_variant, expand_template, fill_template, forward_location, validate_template, LinkVariant,
+    RedirectRule, UtmParams, VisitContext,
+};
+
+/// Longest link title, in characters
+pub const MAX_TITLE_LENGTH: usize = 200;
+/// Longest link description, in characters
+pub const MAX_DESCRIPTION_LENGTH: usize = 1000;
+/// Longest link notes, in characters
+pub const MAX_NOTES_LENGTH: usize = 5000;
+/// Most tags on one link
+pub const MAX_TAGS: usize = 20;
+/// Longest tag, in characters
+pub const MAX_TAG_LENGTH: usize = 50;
+
 /// URL entity representing a shortened URL in the database
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct TinyUrl {
     pub id: i32,
+    /// Domain key, empty for the default domain
+    pub domain: String,
     pub short_code: String,
     pub long_url: String,
     pub qr_code: Option<String>,
     pub clicks: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Time after which the link stops redirecting and may be purged
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Start of the activation window; before it visitors go to `fallback_url`
+    pub active_from: Option<DateTime<Utc>>,
+    /// End of the activation window; from then on visitors go to `fallback_url`
+    pub active_until: Option<DateTime<Utc>>,
+    /// Destination outside the activation window; without one the link is not found there
+    pub fallback_url: Option<String>,
+    /// Why the link was disabled; disabled links do not redirect
+    pub disabled_reason: Option<String>,
+    /// When the link was deleted; the code stays reserved and the link can be restored
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Lowercase labels for grouping and filtering links
+    pub tags: Vec<String>,
+    /// Internal notes, never shown to visitors
+    pub notes: Option<String>,
+    /// Always show the preview page instead of redirecting straight away
+    pub interstitial: bool,
+    /// Per-platform destinations; the first matching rule wins, `long_url` otherwise
+    #[schema(value_type = Vec<RedirectRule>)]
+    pub redirect_rules: Json<Vec<RedirectRule>>,
+    /// Weighted destinations splitting the visitors no redirect rule matched,
+    /// instead of sending them all to `long_url`
+    #[schema(value_type = Vec<LinkVariant>)]
+    pub variants: Json<Vec<LinkVariant>>,
+    /// Variant all visitors are sent to, e.g. the winner of a finished test
+    pub locked_variant: Option<String>,
+    /// Campaign parameters added to every destination of the link
+    #[schema(value_type = UtmParams)]
+    pub utm: Json<UtmParams>,
+    /// Forward the short URL's query string and any path after the code to the destination
+    pub passthrough: bool,
+    /// `long_url` is a template whose `{name}` placeholders are filled from
+    /// path segments after the code or query parameters
+    pub template: bool,
+    /// What the destination page says about itself, fetched in the background
+    /// after the link is created or its destination changes
+    #[schema(value_type = Option<PageMetadata>)]
+    pub page_metadata: Option<Json<PageMetadata>>,
+}
+
+/// Identifies a link across domains, e.g. in variant assignment
+pub fn link_key(domain: &str, short_code: &str) -> String {
+    format!("{}/{}", domain, short_code)
+}
+
+/// What a redirect needs to know about a link, cached per short code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Destination {
+    pub long_url: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interstitial: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RedirectRule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<LinkVariant>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_variant: Option<String>,
+    #[serde(default, skip_serializing_if = "UtmParams::is_empty")]
+    pub utm: UtmParams,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub passthrough: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub template: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_from: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_until: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_url: Option<String>,
+    /// Kept for interstitial links only, so their visits need no database read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<LinkPreview>,
+}
+
+/// What the preview page shows about a link besides where the visitor goes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkPreview {
+    pub long_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_metadata: Option<PageMetadata>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LinkPreview {
+    pub fn of(url: &TinyUrl) -> Self {
+        Self {
+            long_url: url.long_url.clone(),
+            title: url.title.clone(),
+            description: url.description.clone(),
+            page_metadata: url.page_metadata.as_ref().map(|metadata| metadata.0.clone()),
+            created_at: url.created_at,
+        }
+    }
+}
+
+/// Where one visit to a link goes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redirect {
+    pub location: String,
+    /// Show the preview page, continuing to `location`
+    pub interstitial: bool,
+    /// What the preview page shows, when the cached destination has it
+    pub preview: Option<LinkPreview>,
+    /// The location depends on the visitor's `User-Agent`
+    pub varies_by_device: bool,
+    /// The location depends on the visitor's IP address
+    pub varies_by_country: bool,
+    /// Name of the variant picked, if the link is split
+    pub variant: Option<String>,
+    /// The variant was assigned by the visitor ID, which has to be kept
+    pub varies_by_visitor: bool,
+    /// The location changes over time or between visitors, so clients must
+    /// not keep the redirect
+    pub temporary: bool,
+}
+
+impl Destination {
+    pub fn of(url: &TinyUrl) -> Self {
+        Self {
+            long_url: url.long_url.clone(),
+            interstitial: url.interstitial,
+            rules: url.redirect_rules.0.clone(),
+            variants: url.variants.0.clone(),
+            locked_variant: url.locked_variant.clone(),
+            utm: url.utm.0.clone(),
+            passthrough: url.passthrough,
+            template: url.template,
+            active_from: url.active_from,
+            active_until: url.active_until,
+            fallback_url: url.fallback_url.clone(),
+            preview: url.interstitial.then(|| LinkPreview::of(url)),
+        }
+    }
+
+    /// Whether the activation window is open at `now`
+    pub fn is_live(&self, now: DateTime<Utc>) -> bool {
+        in_window(self.active_from, self.active_until, now)
+    }
+
+    /// Where visits outside the activation window go, if anywhere
+    pub fn fallback(&self) -> Option<Redirect> {
+        self.fallback_url.clone().map(|location| Redirect {
+            location,
+            temporary: true,
+            ..Redirect::default()
+        })
+    }
+
+    /// Pick the destination of a visit: the first matching rule's, else the
+    /// locked or assigned variant's, else `long_url`, with template placeholders
+    /// filled in, the link's UTM parameters and, for passthrough links, the
+    /// visit's query and path added. `link` is the link's [`link_key`], so
+    /// visitors are split independently per link.
+    ///
+    /// `None` when the link does not answer the visit: a template is missing
+    /// values, or there is a path after the code that the link does not take.
+    pub fn resolve(self, visit: &VisitContext, link: &str) -> Option<Redirect> {
+        let (values, forwarded) = if self.template {
+            fill_template(&self.long_url, &visit.forwarded)?
+        } else {
+            (HashMap::new(), visit.forwarded.clone())
+        };
+        // Only passthrough links take the rest of the path, and never one
+        // climbing out of the destination path
+        if forwarded.path_suffix.is_some() && !(self.passthrough && forwarded.has_safe_path()) {
+            return None;
+        }
+
+        let (utm, passthrough, template) = (self.utm.clone(), self.passthrough, self.template);
+        let scheduled = self.active_from.is_some() || self.active_until.is_some();
+        let mut redirect = self.pick(visit, link);
+        // A browser keeping a 301 would skip the rules or the split on its next visit
+        redirect.temporary = scheduled
+            || redirect.varies_by_device
+            || redirect.varies_by_country
+            || redirect.varies_by_visitor;
+        if template {
+            redirect.location = expand_template(&redirect.location, &values)?;
+        }
+        redirect.location =
+            forward_location(&redirect.location, &utm, passthrough.then_some(&forwarded));
+        Some(redirect)
+    }
+
+    fn pick(self, visit: &VisitContext, link: &str) -> Redirect {
+        let varies_by_device = self.rules.iter().any(RedirectRule::uses_device);
+        let varies_by_country = self.rules.iter().any(RedirectRule::uses_country);
+        let mut redirect = Redirect {
+            location: self.long_url,
+            interstitial: self.interstitial,
+            preview: self.preview,
+            varies_by_device,
+            varies_by_country,
+            variant: None,
+            varies_by_visitor: false,
+            temporary: false,
+        };
+
+        if let Some(rule) = self.rules.into_iter().find(|rule| rule.matches(visit)) {
+            redirect.location = rule.url;
+            return redirect;
+        }
+
+        let locked = self
+            .locked_variant
+            .as_deref()
+            .and_then(|locked| self.variants.iter().find(|variant| variant.name == locked));
+        let variant = match locked {
+            Some(variant) => Some(variant),
+            None => {
+                redirect.varies_by_visitor = !self.variants.is_empty();
+                // Without an ID the visitor gets a one-off random assignment
+                let visitor_id = visit
+                    .visitor_id
+                    .clone()
+                    .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+                assign_variant(&self.variants, &visitor_id, link)
+            }
+        };
+        if let Some(variant) = variant {
+            redirect.location = variant.url.clone();
+            redirect.variant = Some(variant.name.clone());
+        }
+        redirect
+    }
+
+    pub fn to_cache_value(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| self.long_url.clone())
+    }
+
+    /// Read a cached destination; entries cached before links had options are bare URLs
+    pub fn from_cache_value(value: &str) -> Self {
+        serde_json::from_str(value).unwrap_or_else(|_| Self {
+            long_url: value.to_string(),
+            interstitial: false,
+            rules: Vec::new(),
+            variants: Vec::new(),
+            locked_variant: None,
+            utm: UtmParams::default(),
+            passthrough: false,
+            template: false,
+            active_from: None,
+            active_until: None,
+            fallback_url: None,
+            preview: None,
+        })
+    }
+}
+
+fn in_window(from: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    from.is_none_or(|from| from <= now) && until.is_none_or(|until| now < until)
+}
+
+/// Check an activation window and its fallback URL, describing the first problem found
+pub fn validate_schedule(
+    active_from: Option<DateTime<Utc>>,
+    active_until: Option<DateTime<Utc>>,
+    fallback_url: Option<&str>,
+) -> Result<(), String> {
+    if let (Some(from), Some(until)) = (active_from, active_until) {
+        if from >= until {
+            return Err("active_from must be before active_until".to_string());
+        }
+    }
+    if let Some(fallback_url) = fallback_url {
+        if active_from.is_none() && active_until.is_none() {
+            return Err("fallback_url needs active_from or active_until".to_string());
+        }
+        match url::Url::parse(fallback_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => return Err(format!("invalid fallback_url '{}'", fallback_url)),
+        }
+    }
+    Ok(())
+}
+
+/// Title, Open Graph tags and favicon of a link's destination page
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PageMetadata {
+    /// `og:title`, falling back to the page's `<title>`
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// `og:image` URL
+    pub image: Option<String>,
+    pub site_name: Option<String>,
+    pub favicon: Option<String>,
+    /// Page the fetch ended on after redirects
+    pub final_url: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+    /// Why the page could not be read, in which case the other fields are empty
+    pub error: Option<String>,
+}
+
+/// Descriptive fields of a link, normalized and validated
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+}
+
+impl LinkMetadata {
+    /// Trim the text fields, treating blank ones as absent, and normalize the tags
+    pub fn new<S: AsRef<str>>(
+        title: Option<&str>,
+        description: Option<&str>,
+        tags: &[S],
+        notes: Option<&str>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            title: normalize_text("title", title, MAX_TITLE_LENGTH)?,
+            description: normalize_text("description", description, MAX_DESCRIPTION_LENGTH)?,
+            tags: normalize_tags(tags)?,
+            notes: normalize_text("notes", notes, MAX_NOTES_LENGTH)?,
+        })
+    }
+}
+
+fn normalize_text(field: &str, value: Option<&str>, max: usize) -> Result<Option<String>, String> {
+    let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(None);
+    };
+    if value.chars().count() > max {
+        return Err(format!("{} must be at most {} characters", field, max));
+    }
+    Ok(Some(value.to_string()))
+}
+
+/// Lowercase, trim and dedupe tags, keeping their first-seen order
+pub fn normalize_tags<S: AsRef<str>>(tags: &[S]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.as_ref().trim().to_lowercase();
+        if tag.is_empty() || normalized.contains(&tag) {
+            continue;
+        }
+        if tag.len() > MAX_TAG_LENGTH
+            || !tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+        {
+            return Err(format!(
+                "tag '{}' must be at most {} letters, digits, '-', '_', ':' or '.'",
+                tag, MAX_TAG_LENGTH
+            ));
+        }
+        normalized.push(tag);
+    }
+
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("a link can have at most {} tags", MAX_TAGS));
+    }
+    Ok(normalized)
 }
 
 /// URL statistics
@@ -30,15 +412,92 @@ impl TinyUrl {
         let now = Utc::now();
         Self {
             id: 0,
+            domain: super::DEFAULT_DOMAIN.to_string(),
             short_code,
             long_url,
             qr_code: None,
             clicks: 0,
             created_at: now,
             updated_at: now,
+            expires_at: None,
+            active_from: None,
+            active_until: None,
+            fallback_url: None,
+            disabled_reason: None,
+            deleted_at: None,
+            title: None,
+            description: None,
+            tags: Vec::new(),
+            notes: None,
+            interstitial: false,
+            redirect_rules: Json(Vec::new()),
+            variants: Json(Vec::new()),
+            locked_variant: None,
+            utm: Json(UtmParams::default()),
+            passthrough: false,
+            template: false,
+            page_metadata: None,
+        }
+    }
+
+    pub fn metadata(&self) -> LinkMetadata {
+        LinkMetadata {
+            title: self.title.clone(),
+            description: self.description.clone(),
+            tags: self.tags.clone(),
+            notes: self.notes.clone(),
         }
     }
 
+    pub fn set_metadata(&mut self, metadata: LinkMetadata) {
+        self.title = metadata.title;
+        self.description = metadata.description;
+        self.tags = metadata.tags;
+        self.notes = metadata.notes;
+    }
+
+    /// Check the URLs of a template link, see [`validate_template`]
+    pub fn check_template(&self) -> Result<(), String> {
+        if !self.template {
+            return Ok(());
+        }
+        let others: Vec<&str> = self
+            .redirect_rules
+            .iter()
+            .map(|rule| rule.url.as_str())
+            .chain(self.variants.iter().map(|variant| variant.url.as_str()))
+            .collect();
+        validate_template(&self.long_url, &others)
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_reason.is_some()
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Whether the link was taken down on purpose, as opposed to never existing or expiring
+    pub fn is_retired(&self) -> bool {
+        self.is_disabled() || self.is_deleted()
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Whether the link redirects at `now`
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        !self.is_retired() && !self.is_expired(now)
+    }
+
+    /// Whether the activation window is open at `now`; outside it an active
+    /// link sends visitors to its fallback URL
+    pub fn is_live(&self, now: DateTime<Utc>) -> bool {
+        in_window(self.active_from, self.active_until, now)
+    }
+
     pub fn increment_clicks(&mut self) {
         self.clicks += 1;
         self.updated_at = Utc::now();
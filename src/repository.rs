@@ -1,9 +1,29 @@
-use crate::models::{AppError, Result, TinyUrl};
-use crate::traits::UrlRepository;
+use crate::models::{
+    AppError, ClickEvent, ConflictPolicy, Domain, ImportBatch, ImportIssue, ImportOutcome, ImportRow,
+    LinkFilter, LinkSummary, PageMetadata, Result, TinyUrl, TransferFormat, MAX_IMPORT_ISSUES,
+};
+use crate::traits::{DomainRepository, LinkTransferRepository, UrlRepository};
 use async_trait::async_trait;
-use chrono::Utc;
-use sqlx::PgPool;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::stream::{BoxStream, StreamExt};
+use sqlx::postgres::PgPoolCopyExt;
+use sqlx::types::Json;
+use sqlx::{PgPool, Postgres, QueryBuilder};
 use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::instrument;
+
+/// Columns selected for every `TinyUrl` query
+const URL_COLUMNS: &str =
+    "id, domain, short_code, long_url, qr_code, clicks, created_at, updated_at, expires_at, disabled_reason, deleted_at, \
+     active_from, active_until, fallback_url, title, description, tags, notes, interstitial, redirect_rules, variants, locked_variant, utm, passthrough, template, page_metadata";
+
+/// Columns selected for every `Domain` query
+const DOMAIN_COLUMNS: &str = "id, host, base_url, not_found_url, root_url, created_at";
+
+/// Advisory lock held while rolling up clicks, so concurrent servers take turns
+const CLICK_ROLLUP_LOCK: i64 = 0x7469_6e79_726f_6c6c;
 
 /// High-performance PostgreSQL repository implementation
 pub struct PostgresUrlRepository {
@@ -17,12 +37,14 @@ impl PostgresUrlRepository {
 
     /// Initialize database tables
     pub async fn init(&self) -> Result<()> {
-        // Create the main table with proper indexes
-        sqlx::query(
+        // Create the main table with proper indexes; a multi-statement script
+        // cannot be prepared, so it is sent as raw SQL
+        sqlx::raw_sql(
             r#"
             CREATE TABLE IF NOT EXISTS tinyurls (
                 id SERIAL PRIMARY KEY,
-                short_code VARCHAR(20) NOT NULL UNIQUE,
+                domain VARCHAR(255) NOT NULL DEFAULT '',
+                short_code VARCHAR(20) NOT NULL,
                 long_url TEXT NOT NULL,
                 qr_code TEXT,
                 clicks INTEGER DEFAULT 0,
@@ -30,11 +52,124 @@ impl PostgresUrlRepository {
                 updated_at TIMESTAMPTZ DEFAULT NOW()
             );
             
+            -- Short codes are unique per domain, the empty domain being the default one
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS domain VARCHAR(255) NOT NULL DEFAULT '';
+            ALTER TABLE tinyurls DROP CONSTRAINT IF EXISTS tinyurls_short_code_key;
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_domain_short_code ON tinyurls(domain, short_code);
+            
+            -- Expiry and administrative disabling
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS disabled_reason TEXT;
+            CREATE INDEX IF NOT EXISTS idx_expires_at ON tinyurls(expires_at) WHERE expires_at IS NOT NULL;
+            
+            -- Soft deletion; the row keeps the code reserved until it is released
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+            
+            -- Descriptive metadata; tags are matched with @> through a GIN index
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS title TEXT;
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS description TEXT;
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS notes TEXT;
+            CREATE INDEX IF NOT EXISTS idx_tags ON tinyurls USING GIN (tags);
+            
+            -- Destination page title, Open Graph tags and favicon, fetched in the background
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS page_metadata JSONB;
+            
+            -- Links that always show the preview page before redirecting
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS interstitial BOOLEAN NOT NULL DEFAULT FALSE;
+            
+            -- Ordered per-platform destinations, tried before long_url
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS redirect_rules JSONB NOT NULL DEFAULT '[]';
+            
+            -- Weighted destinations of split links and the variant locked in, if any
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS variants JSONB NOT NULL DEFAULT '[]';
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS locked_variant TEXT;
+            
+            -- Campaign parameters added to destinations, and query and path forwarding
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS utm JSONB NOT NULL DEFAULT '{}';
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS passthrough BOOLEAN NOT NULL DEFAULT FALSE;
+            
+            -- Template links fill {name} placeholders in long_url from the request
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS template BOOLEAN NOT NULL DEFAULT FALSE;
+            
+            -- Activation window, outside of which visitors go to fallback_url
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS active_from TIMESTAMPTZ;
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS active_until TIMESTAMPTZ;
+            ALTER TABLE tinyurls ADD COLUMN IF NOT EXISTS fallback_url TEXT;
+            
             -- Create indexes for performance
             CREATE INDEX IF NOT EXISTS idx_short_code ON tinyurls(short_code);
             CREATE INDEX IF NOT EXISTS idx_long_url ON tinyurls(long_url);
             CREATE INDEX IF NOT EXISTS idx_created_at ON tinyurls(created_at);
             
+            -- Clicks per link and visitor country, counted when GeoIP lookups are on
+            CREATE TABLE IF NOT EXISTS link_countries (
+                url_id INTEGER NOT NULL REFERENCES tinyurls(id) ON DELETE CASCADE,
+                country CHAR(2) NOT NULL,
+                clicks BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (url_id, country)
+            );
+            
+            -- Clicks per link and variant of split links
+            CREATE TABLE IF NOT EXISTS link_variant_clicks (
+                url_id INTEGER NOT NULL REFERENCES tinyurls(id) ON DELETE CASCADE,
+                variant TEXT NOT NULL,
+                clicks BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (url_id, variant)
+            );
+            
+            -- Visits per link by bots, kept out of clicks
+            CREATE TABLE IF NOT EXISTS link_bot_clicks (
+                url_id INTEGER NOT NULL REFERENCES tinyurls(id) ON DELETE CASCADE,
+                bot TEXT NOT NULL,
+                clicks BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (url_id, bot)
+            );
+            
+            -- Human clicks, rolled up into the counts below and pruned after retention
+            CREATE TABLE IF NOT EXISTS click_events (
+                id BIGSERIAL PRIMARY KEY,
+                url_id INTEGER NOT NULL REFERENCES tinyurls(id) ON DELETE CASCADE,
+                clicked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                referrer TEXT,
+                country CHAR(2),
+                device TEXT NOT NULL,
+                browser TEXT NOT NULL,
+                rolled_up BOOLEAN NOT NULL DEFAULT FALSE
+            );
+            CREATE INDEX IF NOT EXISTS idx_click_events_pending ON click_events(id) WHERE NOT rolled_up;
+            CREATE INDEX IF NOT EXISTS idx_click_events_clicked_at ON click_events(clicked_at);
+            
+            -- Clicks per link and UTC hour or day: the total under dimension 'total'
+            -- and breakdowns under 'referrer', 'country', 'device' and 'browser'
+            CREATE TABLE IF NOT EXISTS click_rollups_hourly (
+                url_id INTEGER NOT NULL REFERENCES tinyurls(id) ON DELETE CASCADE,
+                bucket TIMESTAMPTZ NOT NULL,
+                dimension TEXT NOT NULL,
+                value TEXT NOT NULL,
+                clicks BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (url_id, bucket, dimension, value)
+            );
+            CREATE INDEX IF NOT EXISTS idx_click_rollups_hourly_bucket ON click_rollups_hourly(bucket);
+            CREATE TABLE IF NOT EXISTS click_rollups_daily (
+                url_id INTEGER NOT NULL REFERENCES tinyurls(id) ON DELETE CASCADE,
+                bucket TIMESTAMPTZ NOT NULL,
+                dimension TEXT NOT NULL,
+                value TEXT NOT NULL,
+                clicks BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (url_id, bucket, dimension, value)
+            );
+            
+            -- Custom domains served by this deployment
+            CREATE TABLE IF NOT EXISTS domains (
+                id SERIAL PRIMARY KEY,
+                host VARCHAR(255) NOT NULL UNIQUE,
+                base_url TEXT NOT NULL,
+                not_found_url TEXT,
+                root_url TEXT,
+                created_at TIMESTAMPTZ DEFAULT NOW()
+            );
+            
             -- Create a trigger to automatically update updated_at
             CREATE OR REPLACE FUNCTION update_updated_at_column()
             RETURNS TRIGGER AS $$
@@ -54,41 +189,67 @@ impl PostgresUrlRepository {
         .execute(&*self.pool)
         .await?;
 
-        log::info!("Database tables initialized successfully");
+        tracing::info!("Database tables initialized successfully");
         Ok(())
     }
 }
 
 #[async_trait]
 impl UrlRepository for PostgresUrlRepository {
+    #[instrument(name = "db.create", skip(self, url), fields(short_code = %url.short_code))]
     async fn create(&self, url: &TinyUrl) -> Result<TinyUrl> {
-        let result = sqlx::query_as::<_, TinyUrl>(
+        let result = sqlx::query_as::<_, TinyUrl>(&format!(
             r#"
-            INSERT INTO tinyurls (short_code, long_url, qr_code, clicks, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id, short_code, long_url, qr_code, clicks, created_at, updated_at
+            INSERT INTO tinyurls (domain, short_code, long_url, qr_code, clicks, created_at, updated_at, expires_at, disabled_reason, deleted_at,
+                title, description, tags, notes, interstitial, redirect_rules, variants, locked_variant, utm, passthrough,
+                template, active_from, active_until, fallback_url)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21,
+                $22, $23, $24)
+            RETURNING {}
             "#,
-        )
+            URL_COLUMNS
+        ))
+        .bind(&url.domain)
         .bind(&url.short_code)
         .bind(&url.long_url)
         .bind(&url.qr_code)
         .bind(url.clicks)
         .bind(url.created_at)
         .bind(url.updated_at)
+        .bind(url.expires_at)
+        .bind(&url.disabled_reason)
+        .bind(url.deleted_at)
+        .bind(&url.title)
+        .bind(&url.description)
+        .bind(&url.tags)
+        .bind(&url.notes)
+        .bind(url.interstitial)
+        .bind(&url.redirect_rules)
+        .bind(&url.variants)
+        .bind(&url.locked_variant)
+        .bind(&url.utm)
+        .bind(url.passthrough)
+        .bind(url.template)
+        .bind(url.active_from)
+        .bind(url.active_until)
+        .bind(&url.fallback_url)
         .fetch_one(&*self.pool)
         .await?;
 
         Ok(result)
     }
 
-    async fn find_by_short_code(&self, short_code: &str) -> Result<Option<TinyUrl>> {
-        let result = sqlx::query_as::<_, TinyUrl>(
+    #[instrument(name = "db.find_by_short_code", skip(self))]
+    async fn find_by_short_code(&self, domain: &str, short_code: &str) -> Result<Option<TinyUrl>> {
+        let result = sqlx::query_as::<_, TinyUrl>(&format!(
             r#"
-            SELECT id, short_code, long_url, qr_code, clicks, created_at, updated_at
+            SELECT {}
             FROM tinyurls
-            WHERE short_code = $1
+            WHERE domain = $1 AND short_code = $2
             "#,
-        )
+            URL_COLUMNS
+        ))
+        .bind(domain)
         .bind(short_code)
         .fetch_optional(&*self.pool)
         .await?;
@@ -96,16 +257,19 @@ impl UrlRepository for PostgresUrlRepository {
         Ok(result)
     }
 
-    async fn find_by_long_url(&self, long_url: &str) -> Result<Option<TinyUrl>> {
-        let result = sqlx::query_as::<_, TinyUrl>(
+    #[instrument(name = "db.find_by_long_url", skip(self))]
+    async fn find_by_long_url(&self, domain: &str, long_url: &str) -> Result<Option<TinyUrl>> {
+        let result = sqlx::query_as::<_, TinyUrl>(&format!(
             r#"
-            SELECT id, short_code, long_url, qr_code, clicks, created_at, updated_at
+            SELECT {}
             FROM tinyurls
-            WHERE long_url = $1
+            WHERE domain = $1 AND long_url = $2
             ORDER BY created_at DESC
             LIMIT 1
             "#,
-        )
+            URL_COLUMNS
+        ))
+        .bind(domain)
         .bind(long_url)
         .fetch_optional(&*self.pool)
         .await?;
@@ -113,54 +277,964 @@ impl UrlRepository for PostgresUrlRepository {
         Ok(result)
     }
 
+    #[instrument(name = "db.update", skip(self, url), fields(short_code = %url.short_code))]
     async fn update(&self, url: &TinyUrl) -> Result<TinyUrl> {
-        let result = sqlx::query_as::<_, TinyUrl>(
+        let result = sqlx::query_as::<_, TinyUrl>(&format!(
             r#"
             UPDATE tinyurls
-            SET long_url = $2, qr_code = $3, clicks = $4, updated_at = $5
-            WHERE short_code = $1
-            RETURNING id, short_code, long_url, qr_code, clicks, created_at, updated_at
+            SET long_url = $3, qr_code = $4, clicks = $5, updated_at = $6,
+                expires_at = $7, disabled_reason = $8, deleted_at = $9,
+                title = $10, description = $11, tags = $12, notes = $13, interstitial = $14,
+                redirect_rules = $15, variants = $16, locked_variant = $17, utm = $18, passthrough = $19,
+                template = $20, active_from = $21, active_until = $22, fallback_url = $23,
+                -- Page metadata describes the old destination once it changes
+                page_metadata = CASE WHEN long_url = $3 THEN page_metadata END
+            WHERE domain = $1 AND short_code = $2
+            RETURNING {}
             "#,
-        )
+            URL_COLUMNS
+        ))
+        .bind(&url.domain)
         .bind(&url.short_code)
         .bind(&url.long_url)
         .bind(&url.qr_code)
         .bind(url.clicks)
         .bind(url.updated_at)
+        .bind(url.expires_at)
+        .bind(&url.disabled_reason)
+        .bind(url.deleted_at)
+        .bind(&url.title)
+        .bind(&url.description)
+        .bind(&url.tags)
+        .bind(&url.notes)
+        .bind(url.interstitial)
+        .bind(&url.redirect_rules)
+        .bind(&url.variants)
+        .bind(&url.locked_variant)
+        .bind(&url.utm)
+        .bind(url.passthrough)
+        .bind(url.template)
+        .bind(url.active_from)
+        .bind(url.active_until)
+        .bind(&url.fallback_url)
         .fetch_one(&*self.pool)
         .await?;
 
         Ok(result)
     }
 
-    async fn delete_by_short_code(&self, short_code: &str) -> Result<bool> {
+    #[instrument(name = "db.set_page_metadata", skip(self, metadata))]
+    async fn set_page_metadata(
+        &self,
+        domain: &str,
+        short_code: &str,
+        long_url: &str,
+        metadata: &PageMetadata,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE tinyurls SET page_metadata = $4
+            WHERE domain = $1 AND short_code = $2 AND long_url = $3
+            "#,
+        )
+        .bind(domain)
+        .bind(short_code)
+        .bind(long_url)
+        .bind(Json(metadata))
+        .execute(&*self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(name = "db.increment_clicks", skip(self))]
+    async fn increment_clicks(&self, domain: &str, short_code: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE tinyurls SET clicks = clicks + 1 WHERE domain = $1 AND short_code = $2
+            "#,
+        )
+        .bind(domain)
+        .bind(short_code)
+        .execute(&*self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "db.country_clicks", skip(self))]
+    async fn country_clicks(&self, domain: &str, short_code: &str) -> Result<Vec<(String, i64)>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT c.country::text, c.clicks
+            FROM link_countries c JOIN tinyurls t ON t.id = c.url_id
+            WHERE t.domain = $1 AND t.short_code = $2
+            ORDER BY c.clicks DESC, c.country
+            "#,
+        )
+        .bind(domain)
+        .bind(short_code)
+        .fetch_all(&*self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    #[instrument(name = "db.variant_clicks", skip(self))]
+    async fn variant_clicks(&self, domain: &str, short_code: &str) -> Result<Vec<(String, i64)>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT v.variant, v.clicks
+            FROM link_variant_clicks v JOIN tinyurls t ON t.id = v.url_id
+            WHERE t.domain = $1 AND t.short_code = $2
+            "#,
+        )
+        .bind(domain)
+        .bind(short_code)
+        .fetch_all(&*self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    #[instrument(name = "db.record_bot_click", skip(self))]
+    async fn record_bot_click(&self, domain: &str, short_code: &str, bot: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO link_bot_clicks (url_id, bot, clicks)
+            SELECT id, $3, 1 FROM tinyurls WHERE domain = $1 AND short_code = $2
+            ON CONFLICT (url_id, bot) DO UPDATE SET clicks = link_bot_clicks.clicks + 1
+            "#,
+        )
+        .bind(domain)
+        .bind(short_code)
+        .bind(bot)
+        .execute(&*self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "db.bot_clicks", skip(self))]
+    async fn bot_clicks(&self, domain: &str, short_code: &str) -> Result<Vec<(String, i64)>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT b.bot, b.clicks
+            FROM link_bot_clicks b JOIN tinyurls t ON t.id = b.url_id
+            WHERE t.domain = $1 AND t.short_code = $2
+            "#,
+        )
+        .bind(domain)
+        .bind(short_code)
+        .fetch_all(&*self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    #[instrument(name = "db.record_click_event", skip(self, event))]
+    async fn record_click_event(
+        &self,
+        domain: &str,
+        short_code: &str,
+        event: &ClickEvent,
+        variant: Option<&str>,
+    ) -> Result<()> {
+        // Data-modifying CTEs all run, so the link is looked up once for every write
+        sqlx::query(
+            r#"
+            WITH link AS (
+                SELECT id FROM tinyurls WHERE domain = $1 AND short_code = $2
+            ), event AS (
+                INSERT INTO click_events (url_id, referrer, country, device, browser)
+                SELECT id, $3, $4, $5, $6 FROM link
+            ), country AS (
+                INSERT INTO link_countries (url_id, country, clicks)
+                SELECT id, $4, 1 FROM link WHERE $4 IS NOT NULL
+                ON CONFLICT (url_id, country) DO UPDATE SET clicks = link_countries.clicks + 1
+            ), variant AS (
+                INSERT INTO link_variant_clicks (url_id, variant, clicks)
+                SELECT id, $7, 1 FROM link WHERE $7 IS NOT NULL
+                ON CONFLICT (url_id, variant) DO UPDATE SET clicks = link_variant_clicks.clicks + 1
+            )
+            SELECT 1
+            "#,
+        )
+        .bind(domain)
+        .bind(short_code)
+        .bind(&event.referrer)
+        .bind(&event.country)
+        .bind(event.device.as_str())
+        .bind(event.browser.as_str())
+        .bind(variant)
+        .execute(&*self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "db.roll_up_clicks", skip(self))]
+    async fn roll_up_clicks(&self, limit: i64) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_xact_lock($1)")
+            .bind(CLICK_ROLLUP_LOCK)
+            .fetch_one(&mut *tx)
+            .await?;
+        if !locked {
+            return Ok(0);
+        }
+
+        // Data-modifying CTEs all run once, whether the final SELECT reads them or not
+        let rolled_up: i64 = sqlx::query_scalar(
+            r#"
+            WITH batch AS (
+                UPDATE click_events SET rolled_up = TRUE
+                WHERE id IN (
+                    SELECT id FROM click_events WHERE NOT rolled_up ORDER BY id LIMIT $1
+                )
+                RETURNING url_id, clicked_at, referrer, country, device, browser
+            ), counted AS (
+                SELECT b.url_id, b.clicked_at, d.dimension, d.value
+                FROM batch b CROSS JOIN LATERAL (VALUES
+                    ('total', ''),
+                    ('referrer', b.referrer),
+                    ('country', b.country::text),
+                    ('device', b.device),
+                    ('browser', b.browser)
+                ) AS d(dimension, value)
+                WHERE d.value IS NOT NULL
+            ), hourly AS (
+                INSERT INTO click_rollups_hourly (url_id, bucket, dimension, value, clicks)
+                SELECT url_id, date_trunc('hour', clicked_at, 'UTC'), dimension, value, COUNT(*)
+                FROM counted GROUP BY 1, 2, 3, 4
+                ON CONFLICT (url_id, bucket, dimension, value)
+                DO UPDATE SET clicks = click_rollups_hourly.clicks + EXCLUDED.clicks
+            ), daily AS (
+                INSERT INTO click_rollups_daily (url_id, bucket, dimension, value, clicks)
+                SELECT url_id, date_trunc('day', clicked_at, 'UTC'), dimension, value, COUNT(*)
+                FROM counted GROUP BY 1, 2, 3, 4
+                ON CONFLICT (url_id, bucket, dimension, value)
+                DO UPDATE SET clicks = click_rollups_daily.clicks + EXCLUDED.clicks
+            )
+            SELECT COUNT(*) FROM batch
+            "#,
+        )
+        .bind(limit)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(rolled_up as u64)
+    }
+
+    #[instrument(name = "db.prune_click_events", skip(self))]
+    async fn prune_click_events(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM click_events WHERE rolled_up AND clicked_at < $1")
+            .bind(before)
+            .execute(&*self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(name = "db.prune_hourly_rollups", skip(self))]
+    async fn prune_hourly_rollups(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM click_rollups_hourly WHERE bucket < $1")
+            .bind(before)
+            .execute(&*self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(name = "db.click_rollups", skip(self))]
+    async fn click_rollups(
+        &self,
+        domain: &str,
+        short_code: &str,
+        hourly: bool,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, String, String, i64)>> {
+        let table = if hourly { "click_rollups_hourly" } else { "click_rollups_daily" };
+        let rows = sqlx::query_as::<_, (DateTime<Utc>, String, String, i64)>(&format!(
+            r#"
+            SELECT r.bucket, r.dimension, r.value, r.clicks
+            FROM {} r JOIN tinyurls t ON t.id = r.url_id
+            WHERE t.domain = $1 AND t.short_code = $2 AND r.bucket >= $3 AND r.bucket < $4
+            ORDER BY r.bucket
+            "#,
+            table
+        ))
+        .bind(domain)
+        .bind(short_code)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&*self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    #[instrument(name = "db.delete_by_short_code", skip(self))]
+    async fn delete_by_short_code(&self, domain: &str, short_code: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE tinyurls SET deleted_at = NOW()
+            WHERE domain = $1 AND short_code = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(domain)
+        .bind(short_code)
+        .execute(&*self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(name = "db.release_code", skip(self))]
+    async fn release_code(
+        &self,
+        domain: &str,
+        short_code: &str,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<bool> {
         let result = sqlx::query(
             r#"
-            DELETE FROM tinyurls WHERE short_code = $1
+            DELETE FROM tinyurls WHERE domain = $1 AND short_code = $2 AND deleted_at <= $3
             "#,
         )
+        .bind(domain)
         .bind(short_code)
+        .bind(deleted_before)
         .execute(&*self.pool)
         .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    async fn get_stats(&self, short_code: &str) -> Result<Option<TinyUrl>> {
+    #[instrument(name = "db.get_stats", skip(self))]
+    async fn get_stats(&self, domain: &str, short_code: &str) -> Result<Option<TinyUrl>> {
         // Same as find_by_short_code for now
-        self.find_by_short_code(short_code).await
+        self.find_by_short_code(domain, short_code).await
     }
 
-    async fn exists(&self, short_code: &str) -> Result<bool> {
+    #[instrument(name = "db.exists", skip(self))]
+    async fn exists(&self, domain: &str, short_code: &str) -> Result<bool> {
         let result = sqlx::query(
             r#"
-            SELECT 1 FROM tinyurls WHERE short_code = $1 LIMIT 1
+            SELECT 1 FROM tinyurls WHERE domain = $1 AND short_code = $2 LIMIT 1
             "#,
         )
+        .bind(domain)
         .bind(short_code)
         .fetch_optional(&*self.pool)
         .await?;
 
         Ok(result.is_some())
     }
+
+    #[instrument(name = "db.existing_codes", skip(self))]
+    async fn existing_codes(&self, domain: &str, short_codes: &[String]) -> Result<Vec<String>> {
+        let codes = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT short_code FROM tinyurls WHERE domain = $1 AND short_code = ANY($2)
+            "#,
+        )
+        .bind(domain)
+        .bind(short_codes)
+        .fetch_all(&*self.pool)
+        .await?;
+
+        Ok(codes)
+    }
+
+    #[instrument(name = "db.list_page", skip(self))]
+    async fn list_page(&self, after_id: i32, limit: i64) -> Result<Vec<TinyUrl>> {
+        let result = sqlx::query_as::<_, TinyUrl>(&format!(
+            r#"
+            SELECT {}
+            FROM tinyurls
+            WHERE id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+            URL_COLUMNS
+        ))
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&*self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    #[instrument(name = "db.list_links", skip(self))]
+    async fn list_links(&self, filter: &LinkFilter) -> Result<Vec<TinyUrl>> {
+        let mut query = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {} FROM tinyurls WHERE TRUE",
+            URL_COLUMNS
+        ));
+        if let Some(domain) = &filter.domain {
+            query.push(" AND domain = ").push_bind(domain.clone());
+        }
+        if !filter.tags.is_empty() {
+            query.push(" AND tags @> ").push_bind(filter.tags.clone());
+        }
+        if let Some(search) = &filter.search {
+            let pattern = format!("%{}%", escape_like(search));
+            query
+                .push(" AND (short_code ILIKE ")
+                .push_bind(pattern.clone())
+                .push(" OR title ILIKE ")
+                .push_bind(pattern.clone())
+                .push(" OR long_url ILIKE ")
+                .push_bind(pattern)
+                .push(")");
+        }
+        if !filter.include_deleted {
+            query.push(" AND deleted_at IS NULL");
+        }
+        if let Some(before) = filter.before {
+            query.push(" AND id < ").push_bind(before);
+        }
+        query.push(" ORDER BY id DESC LIMIT ").push_bind(filter.limit);
+
+        let result = query
+            .build_query_as::<TinyUrl>()
+            .fetch_all(&*self.pool)
+            .await?;
+
+        Ok(result)
+    }
+
+    #[instrument(name = "db.count_expired", skip(self))]
+    async fn count_expired(&self, before: DateTime<Utc>) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM tinyurls WHERE expires_at <= $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(before)
+        .fetch_one(&*self.pool)
+        .await?;
+
+        Ok(count as u64)
+    }
+
+    #[instrument(name = "db.delete_expired", skip(self))]
+    async fn delete_expired(&self, before: DateTime<Utc>) -> Result<Vec<(String, String)>> {
+        let deleted = sqlx::query_as::<_, (String, String)>(
+            r#"
+            UPDATE tinyurls SET deleted_at = NOW()
+            WHERE expires_at <= $1 AND deleted_at IS NULL
+            RETURNING domain, short_code
+            "#,
+        )
+        .bind(before)
+        .fetch_all(&*self.pool)
+        .await?;
+
+        Ok(deleted)
+    }
+
+    #[instrument(name = "db.summarize", skip(self))]
+    async fn summarize(&self, now: DateTime<Utc>) -> Result<LinkSummary> {
+        let summary = sqlx::query_as::<_, LinkSummary>(
+            r#"
+            SELECT
+                COUNT(*) AS total_links,
+                COUNT(*) FILTER (
+                    WHERE disabled_reason IS NULL AND deleted_at IS NULL
+                        AND (expires_at IS NULL OR expires_at > $1)
+                ) AS active_links,
+                COUNT(*) FILTER (WHERE disabled_reason IS NOT NULL) AS disabled_links,
+                COUNT(*) FILTER (WHERE expires_at <= $1) AS expired_links,
+                COUNT(*) FILTER (WHERE deleted_at IS NOT NULL) AS deleted_links,
+                COALESCE(SUM(clicks), 0)::BIGINT AS total_clicks,
+                (SELECT COUNT(*) FROM domains) AS domains
+            FROM tinyurls
+            "#,
+        )
+        .bind(now)
+        .fetch_one(&*self.pool)
+        .await?;
+
+        Ok(summary)
+    }
+}
+
+#[async_trait]
+impl DomainRepository for PostgresUrlRepository {
+    #[instrument(name = "db.create_domain", skip(self, domain), fields(host = %domain.host))]
+    async fn create_domain(&self, domain: &Domain) -> Result<Domain> {
+        let result = sqlx::query_as::<_, Domain>(&format!(
+            r#"
+            INSERT INTO domains (host, base_url, not_found_url, root_url, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING {}
+            "#,
+            DOMAIN_COLUMNS
+        ))
+        .bind(&domain.host)
+        .bind(&domain.base_url)
+        .bind(&domain.not_found_url)
+        .bind(&domain.root_url)
+        .bind(domain.created_at)
+        .fetch_one(&*self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    #[instrument(name = "db.find_domain", skip(self))]
+    async fn find_domain(&self, host: &str) -> Result<Option<Domain>> {
+        let result = sqlx::query_as::<_, Domain>(&format!(
+            r#"
+            SELECT {}
+            FROM domains
+            WHERE host = $1
+            "#,
+            DOMAIN_COLUMNS
+        ))
+        .bind(host)
+        .fetch_optional(&*self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    #[instrument(name = "db.list_domains", skip(self))]
+    async fn list_domains(&self) -> Result<Vec<Domain>> {
+        let result = sqlx::query_as::<_, Domain>(&format!(
+            r#"
+            SELECT {}
+            FROM domains
+            ORDER BY host
+            "#,
+            DOMAIN_COLUMNS
+        ))
+        .fetch_all(&*self.pool)
+        .await?;
+
+        Ok(result)
+    }
+}
+
+/// Escape `LIKE` wildcards so user input matches literally
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Columns of the import staging table, in COPY order
+const IMPORT_COLUMNS: &str =
+    "line, domain, short_code, long_url, clicks, created_at, updated_at, expires_at, disabled_reason, deleted_at, \
+     title, description, tags, notes, interstitial, redirect_rules, variants, locked_variant, utm, passthrough, template, \
+     active_from, active_until, fallback_url";
+
+/// Link columns written by an import
+const IMPORTED_URL_COLUMNS: &str =
+    "domain, short_code, long_url, clicks, created_at, updated_at, expires_at, disabled_reason, deleted_at, \
+     title, description, tags, notes, interstitial, redirect_rules, variants, locked_variant, utm, passthrough, template, \
+     active_from, active_until, fallback_url";
+
+/// RFC 3339 rendering of a timestamp column in exports
+fn export_timestamp(column: &str) -> String {
+    format!(
+        r#"to_char({} AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US"Z"')"#,
+        column
+    )
+}
+
+/// Append one staging row in COPY text format
+fn write_copy_row(buf: &mut String, row: &ImportRow) {
+    let url = &row.url;
+    let fields = [
+        Some(row.line.to_string()),
+        Some(url.domain.clone()),
+        Some(url.short_code.clone()),
+        Some(url.long_url.clone()),
+        Some(url.clicks.to_string()),
+        Some(url.created_at.to_rfc3339()),
+        Some(url.updated_at.to_rfc3339()),
+        url.expires_at.map(|expires_at| expires_at.to_rfc3339()),
+        url.disabled_reason.clone(),
+        url.deleted_at.map(|deleted_at| deleted_at.to_rfc3339()),
+        url.title.clone(),
+        url.description.clone(),
+        // Normalized tags never need quoting inside an array literal
+        Some(format!("{{{}}}", url.tags.join(","))),
+        url.notes.clone(),
+        Some(url.interstitial.to_string()),
+        Some(serde_json::to_string(&url.redirect_rules).unwrap_or_else(|_| "[]".to_string())),
+        Some(serde_json::to_string(&url.variants).unwrap_or_else(|_| "[]".to_string())),
+        url.locked_variant.clone(),
+        Some(serde_json::to_string(&url.utm).unwrap_or_else(|_| "{}".to_string())),
+        Some(url.passthrough.to_string()),
+        Some(url.template.to_string()),
+        url.active_from.map(|active_from| active_from.to_rfc3339()),
+        url.active_until.map(|active_until| active_until.to_rfc3339()),
+        url.fallback_url.clone(),
+    ];
+
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            buf.push('\t');
+        }
+        match field {
+            Some(value) => {
+                for c in value.chars() {
+                    match c {
+                        '\\' => buf.push_str("\\\\"),
+                        '\t' => buf.push_str("\\t"),
+                        '\n' => buf.push_str("\\n"),
+                        '\r' => buf.push_str("\\r"),
+                        c => buf.push(c),
+                    }
+                }
+            }
+            None => buf.push_str("\\N"),
+        }
+    }
+    buf.push('\n');
+}
+
+/// Undo COPY text escaping of one exported row
+fn unescape_copy_row(row: &[u8]) -> Bytes {
+    let mut out = Vec::with_capacity(row.len());
+    let mut bytes = row.iter().copied();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'n') => out.push(b'\n'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b'b') => out.push(0x08),
+            Some(b'f') => out.push(0x0c),
+            Some(b'v') => out.push(0x0b),
+            Some(other) => out.push(other),
+            None => out.push(b'\\'),
+        }
+    }
+    Bytes::from(out)
+}
+
+/// Turn `(line, short_code, ...)` rows into report issues, up to the report limit
+fn sample_issues(
+    rows: &[(i64, String)],
+    message: impl Fn(&str) -> String,
+    issues: &mut Vec<ImportIssue>,
+) {
+    for (line, short_code) in rows.iter().take(MAX_IMPORT_ISSUES) {
+        issues.push(ImportIssue {
+            line: *line as u64,
+            short_code: Some(short_code.clone()),
+            message: message(short_code),
+        });
+    }
+}
+
+#[async_trait]
+impl LinkTransferRepository for PostgresUrlRepository {
+    #[instrument(name = "db.import_links", skip(self, batches))]
+    async fn import_links(
+        &self,
+        mut batches: mpsc::Receiver<ImportBatch>,
+        on_conflict: ConflictPolicy,
+        dry_run: bool,
+    ) -> Result<ImportOutcome> {
+        let mut outcome = ImportOutcome::default();
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            CREATE TEMP TABLE link_import (
+                line BIGINT NOT NULL,
+                domain TEXT NOT NULL,
+                short_code TEXT NOT NULL,
+                long_url TEXT NOT NULL,
+                clicks INTEGER NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL,
+                expires_at TIMESTAMPTZ,
+                disabled_reason TEXT,
+                deleted_at TIMESTAMPTZ,
+                title TEXT,
+                description TEXT,
+                tags TEXT[] NOT NULL,
+                notes TEXT,
+                interstitial BOOLEAN NOT NULL,
+                redirect_rules JSONB NOT NULL,
+                variants JSONB NOT NULL,
+                locked_variant TEXT,
+                utm JSONB NOT NULL,
+                passthrough BOOLEAN NOT NULL,
+                template BOOLEAN NOT NULL,
+                active_from TIMESTAMPTZ,
+                active_until TIMESTAMPTZ,
+                fallback_url TEXT
+            ) ON COMMIT DROP
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // Stream the parsed rows into the staging table
+        let mut copy = tx
+            .copy_in_raw(&format!("COPY link_import ({}) FROM STDIN", IMPORT_COLUMNS))
+            .await?;
+        let mut complete = false;
+        while let Some(batch) = batches.recv().await {
+            match batch {
+                ImportBatch::Rows(rows) => {
+                    let mut buf = String::new();
+                    for row in &rows {
+                        write_copy_row(&mut buf, row);
+                    }
+                    copy.send(buf.into_bytes()).await?;
+                }
+                ImportBatch::End => {
+                    complete = true;
+                    break;
+                }
+            }
+        }
+        if !complete {
+            copy.abort("import input ended early").await?;
+            return Err(AppError::Internal(
+                "Import input ended before it was fully read".to_string(),
+            ));
+        }
+        outcome.staged = copy.finish().await?;
+
+        sqlx::query("CREATE INDEX ON link_import (domain, short_code, line)")
+            .execute(&mut *tx)
+            .await?;
+
+        // The first occurrence of a code in the input wins
+        let duplicates = sqlx::query_as::<_, (i64, String)>(
+            r#"
+            DELETE FROM link_import l
+            WHERE EXISTS (
+                SELECT 1 FROM link_import f
+                WHERE f.domain = l.domain AND f.short_code = l.short_code AND f.line < l.line
+            )
+            RETURNING l.line, l.short_code
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        outcome.duplicates = duplicates.len() as u64;
+        sample_issues(
+            &duplicates,
+            |_| "short code repeats an earlier row".to_string(),
+            &mut outcome.issues,
+        );
+
+        let unknown_domains = sqlx::query_as::<_, (i64, String, String)>(
+            r#"
+            DELETE FROM link_import l
+            WHERE l.domain <> '' AND NOT EXISTS (SELECT 1 FROM domains d WHERE d.host = l.domain)
+            RETURNING l.line, l.short_code, l.domain
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        outcome.unknown_domains = unknown_domains.len() as u64;
+        for (line, short_code, domain) in unknown_domains.iter().take(MAX_IMPORT_ISSUES) {
+            outcome.issues.push(ImportIssue {
+                line: *line as u64,
+                short_code: Some(short_code.clone()),
+                message: format!("unknown domain '{}'", domain),
+            });
+        }
+
+        let conflicts = sqlx::query_as::<_, (i64, String, String)>(
+            r#"
+            SELECT l.line, l.domain, l.short_code
+            FROM link_import l
+            JOIN tinyurls t ON t.domain = l.domain AND t.short_code = l.short_code
+            ORDER BY l.line
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        outcome.conflicts = conflicts.len() as u64;
+        let conflict_message = match on_conflict {
+            ConflictPolicy::Skip => "short code already exists, kept the existing link",
+            ConflictPolicy::Overwrite => "short code already exists, replaced the existing link",
+            ConflictPolicy::Fail => "short code already exists",
+        };
+        let conflict_rows: Vec<(i64, String)> = conflicts
+            .iter()
+            .take(MAX_IMPORT_ISSUES)
+            .map(|(line, _, short_code)| (*line, short_code.clone()))
+            .collect();
+        sample_issues(&conflict_rows, |_| conflict_message.to_string(), &mut outcome.issues);
+        outcome.issues.sort_by_key(|issue| issue.line);
+        outcome.issues.truncate(MAX_IMPORT_ISSUES);
+
+        let remaining = outcome.staged - outcome.duplicates - outcome.unknown_domains;
+        match on_conflict {
+            ConflictPolicy::Fail if outcome.conflicts > 0 => {
+                if !dry_run {
+                    let (line, _, short_code) = &conflicts[0];
+                    return Err(AppError::AlreadyExists(format!(
+                        "{} imported short codes already exist, first '{}' on line {}; nothing was imported",
+                        outcome.conflicts, short_code, line
+                    )));
+                }
+                // The real import fails as a whole, so report the conflicts and not
+                // the other rows as skipped
+                outcome.failed = true;
+            }
+            ConflictPolicy::Fail | ConflictPolicy::Skip => {
+                let result = sqlx::query(&format!(
+                    r#"
+                    INSERT INTO tinyurls ({columns})
+                    SELECT {columns} FROM link_import ORDER BY line
+                    ON CONFLICT (domain, short_code) DO NOTHING
+                    "#,
+                    columns = IMPORTED_URL_COLUMNS
+                ))
+                .execute(&mut *tx)
+                .await?;
+                outcome.inserted = result.rows_affected();
+                outcome.skipped = remaining - outcome.inserted;
+            }
+            ConflictPolicy::Overwrite => {
+                sqlx::query(&format!(
+                    r#"
+                    INSERT INTO tinyurls ({columns})
+                    SELECT {columns} FROM link_import ORDER BY line
+                    ON CONFLICT (domain, short_code) DO UPDATE SET
+                        long_url = EXCLUDED.long_url,
+                        clicks = EXCLUDED.clicks,
+                        created_at = EXCLUDED.created_at,
+                        expires_at = EXCLUDED.expires_at,
+                        disabled_reason = EXCLUDED.disabled_reason,
+                        deleted_at = EXCLUDED.deleted_at,
+                        title = EXCLUDED.title,
+                        description = EXCLUDED.description,
+                        tags = EXCLUDED.tags,
+                        notes = EXCLUDED.notes,
+                        interstitial = EXCLUDED.interstitial,
+                        redirect_rules = EXCLUDED.redirect_rules,
+                        variants = EXCLUDED.variants,
+                        locked_variant = EXCLUDED.locked_variant,
+                        utm = EXCLUDED.utm,
+                        passthrough = EXCLUDED.passthrough,
+                        template = EXCLUDED.template,
+                        active_from = EXCLUDED.active_from,
+                        active_until = EXCLUDED.active_until,
+                        fallback_url = EXCLUDED.fallback_url,
+                        page_metadata = CASE
+                            WHEN tinyurls.long_url = EXCLUDED.long_url THEN tinyurls.page_metadata
+                        END
+                    "#,
+                    columns = IMPORTED_URL_COLUMNS
+                ))
+                .execute(&mut *tx)
+                .await?;
+                outcome.updated = outcome.conflicts;
+                outcome.inserted = remaining - outcome.conflicts;
+                outcome.overwritten = conflicts
+                    .into_iter()
+                    .map(|(_, domain, short_code)| (domain, short_code))
+                    .collect();
+            }
+        }
+
+        if dry_run {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+
+        Ok(outcome)
+    }
+
+    #[instrument(name = "db.export_links", skip(self))]
+    async fn export_links(&self, format: TransferFormat) -> Result<BoxStream<'static, Result<Bytes>>> {
+        let timestamps = [
+            export_timestamp("created_at"),
+            export_timestamp("updated_at"),
+            export_timestamp("expires_at"),
+            export_timestamp("deleted_at"),
+            export_timestamp("active_from"),
+            export_timestamp("active_until"),
+        ];
+
+        let stream = match format {
+            TransferFormat::Csv => self
+                .pool
+                .copy_out_raw(&format!(
+                    r#"
+                    COPY (
+                        SELECT domain, short_code, long_url, clicks,
+                            {} AS created_at, {} AS updated_at, {} AS expires_at, disabled_reason,
+                            {} AS deleted_at, title, description,
+                            array_to_string(tags, ',') AS tags, notes, interstitial::text AS interstitial,
+                            redirect_rules::text AS redirect_rules, variants::text AS variants, locked_variant,
+                            utm::text AS utm, passthrough::text AS passthrough,
+                            template::text AS template, {} AS active_from, {} AS active_until, fallback_url
+                        FROM tinyurls
+                        ORDER BY id
+                    ) TO STDOUT WITH (FORMAT csv, HEADER)
+                    "#,
+                    timestamps[0], timestamps[1], timestamps[2], timestamps[3], timestamps[4], timestamps[5]
+                ))
+                .await?
+                .map(|chunk| chunk.map_err(AppError::from))
+                .boxed(),
+            // COPY OUT sends one message per row, so each chunk unescapes on its own
+            TransferFormat::Jsonl => self
+                .pool
+                .copy_out_raw(&format!(
+                    r#"
+                    COPY (
+                        SELECT json_build_object(
+                            'domain', domain,
+                            'short_code', short_code,
+                            'long_url', long_url,
+                            'clicks', clicks,
+                            'created_at', {},
+                            'updated_at', {},
+                            'expires_at', {},
+                            'disabled_reason', disabled_reason,
+                            'deleted_at', {},
+                            'title', title,
+                            'description', description,
+                            'tags', to_json(tags),
+                            'notes', notes,
+                            'interstitial', interstitial,
+                            'redirect_rules', redirect_rules,
+                            'variants', variants,
+                            'locked_variant', locked_variant,
+                            'utm', utm,
+                            'passthrough', passthrough,
+                            'template', template,
+                            'active_from', {},
+                            'active_until', {},
+                            'fallback_url', fallback_url
+                        )::text
+                        FROM tinyurls
+                        ORDER BY id
+                    ) TO STDOUT
+                    "#,
+                    timestamps[0], timestamps[1], timestamps[2], timestamps[3], timestamps[4], timestamps[5]
+                ))
+                .await?
+                .map(|chunk| chunk.map(|row| unescape_copy_row(&row)).map_err(AppError::from))
+                .boxed(),
+        };
+
+        Ok(stream)
+    }
 }
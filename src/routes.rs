@@ -1,14 +1,34 @@
+use crate::core::bots::BotDetector;
+use crate::core::db_connect::DatabaseManager;
+use crate::core::health;
+use crate::core::metrics;
+use crate::core::preview::preview_response;
+use crate::core::public_url::PublicUrl;
+use crate::core::request_id;
 use crate::models::{
-    CreateUrlRequest, CreateUrlResponse, ErrorResponse, HealthResponse, UrlStatsResponse, AppError,
+    normalize_host, AppError, CodeAvailabilityResponse, ConflictPolicy, CreateDomainRequest,
+    CreateUrlRequest, ComponentHealth, CreateUrlResponse, DisableUrlRequest, Domain, DomainQuery,
+    ErrorResponse, ExportOptions, Forwarded, HealthResponse, ImportIssue, ImportOptions, ImportReport,
+    Destination, link_key, LinkListQuery, LinkPreview, LinkListResponse, LinkVariant, PageMetadata, Redirect,
+    RedirectRule, TimeseriesInterval, TimeseriesPoint, TimeseriesQuery, TimeseriesResponse, TinyUrl,
+    TransferFormat, UtmParams, VariantStats, VisitContext, UrlStatsResponse, DEFAULT_DISABLED_REASON,
 };
-use crate::services::{DefaultUrlService, RedisCacheService, DefaultShortCodeGenerator};
+use crate::services::{ChannelReader, DefaultUrlService, RedisCacheService, ShortCodeStrategy};
 use crate::repository::PostgresUrlRepository;
-use crate::traits::{UrlService};
+use crate::traits::{GeoLocator, UrlService};
+use actix_web::cookie::time::Duration as CookieDuration;
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::http::header;
 use actix_web::{
-    delete, get, post, web, HttpResponse, Responder, Result as ActixResult, ResponseError,
+    delete, get, post, route, web, HttpRequest, HttpResponse, Responder, Result as ActixResult,
+    ResponseError,
 };
-use chrono::Utc;
+use futures_util::StreamExt;
+use std::io;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::instrument;
 use utoipa::OpenApi;
 
 /// OpenAPI documentation
@@ -19,7 +39,26 @@ use utoipa::OpenApi;
             CreateUrlRequest,
             CreateUrlResponse,
             UrlStatsResponse,
+            CodeAvailabilityResponse,
+            CreateDomainRequest,
+            Domain,
+            TinyUrl,
+            PageMetadata,
+            RedirectRule,
+            LinkVariant,
+            UtmParams,
+            VariantStats,
+            TimeseriesResponse,
+            TimeseriesPoint,
+            TimeseriesInterval,
+            LinkListResponse,
+            DisableUrlRequest,
             HealthResponse,
+            ComponentHealth,
+            ImportReport,
+            ImportIssue,
+            ConflictPolicy,
+            TransferFormat,
             ErrorResponse,
         )
     ),
@@ -37,69 +76,411 @@ pub struct ApiDoc;
 /// Application state containing services
 #[derive(Clone)]
 pub struct AppState {
-    pub url_service: Arc<DefaultUrlService<PostgresUrlRepository, RedisCacheService, DefaultShortCodeGenerator>>,
+    pub db: Arc<DatabaseManager>,
+    pub cache: Arc<RedisCacheService>,
+    pub public_url: Arc<PublicUrl>,
+    /// Deadline for each dependency check in `/health/ready`
+    pub readiness_timeout: Duration,
+    /// Bearer token for `/admin` endpoints, which are disabled when `None`
+    pub admin_token: Option<Arc<str>>,
+    /// Country lookups of visitors, off when no GeoIP database is configured
+    pub geoip: Option<Arc<dyn GeoLocator + Send + Sync>>,
+    /// Tells bots' visits from people's
+    pub bots: Arc<BotDetector>,
+    pub url_service: Arc<DefaultUrlService<PostgresUrlRepository, RedisCacheService, ShortCodeStrategy>>,
 }
 
-/// Health check endpoint
+/// Domain key selected by the optional `domain` query parameter
+fn query_domain(query: &DomainQuery) -> String {
+    query.domain.as_deref().map(normalize_host).unwrap_or_default()
+}
+
+/// Cookie keeping a browser on the same variant of split links
+const VISITOR_COOKIE: &str = "tinyurl_vid";
+
+/// Visitor ID from the request's cookie, if it holds a well-formed one
+fn visitor_cookie(req: &HttpRequest) -> Option<String> {
+    req.cookie(VISITOR_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|id| {
+            !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Host of the page linking to the short URL, from the `Referer` header
+fn referrer_host(req: &HttpRequest) -> Option<String> {
+    let referrer = req.headers().get(header::REFERER)?.to_str().ok()?;
+    let host = url::Url::parse(referrer).ok()?.host_str()?.to_ascii_lowercase();
+    (host.len() <= 253).then_some(host)
+}
+
+/// What the request tells about the visitor; visitors without a cookie get a
+/// new ID, which is only kept if a split link needs it, and are counted as
+/// unique visitors by their address and user agent
+fn visit_context(req: &HttpRequest, data: &AppState, path_suffix: Option<String>) -> VisitContext {
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+    let client_ip = data.public_url.client_ip(req);
+    let country = data
+        .geoip
+        .as_ref()
+        .zip(client_ip)
+        .and_then(|(geoip, ip)| geoip.country(ip));
+    let cookie = visitor_cookie(req);
+    let identity = match &cookie {
+        Some(id) => format!("id:{}", id),
+        None => format!(
+            "ip:{}|ua:{}",
+            client_ip.map(|ip| ip.to_string()).unwrap_or_default(),
+            user_agent.unwrap_or_default()
+        ),
+    };
+    let visitor_id = cookie.unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+    VisitContext::from_user_agent(user_agent)
+        .with_country(country)
+        .with_visitor_id(Some(visitor_id))
+        .with_visitor_identity(&identity)
+        .with_bot(data.bots.classify(req))
+        .with_referrer(referrer_host(req))
+        .with_forwarded(Forwarded {
+            query: Some(req.query_string().to_string()).filter(|query| !query.is_empty()),
+            path_suffix,
+        })
+}
+
+/// Mark a response whose destination depends on the visitor as such, and keep
+/// new visitors' IDs so split links send them to the same variant next time
+fn vary_response(
+    mut response: HttpResponse,
+    req: &HttpRequest,
+    data: &AppState,
+    visit: &VisitContext,
+    redirect: &Redirect,
+) -> HttpResponse {
+    if redirect.varies_by_device {
+        response
+            .headers_mut()
+            .insert(header::VARY, header::HeaderValue::from_static("User-Agent"));
+    }
+    // Shared caches cannot tell visitors' countries or IDs apart
+    if (redirect.varies_by_country || redirect.varies_by_visitor)
+        && !response.headers().contains_key(header::CACHE_CONTROL)
+    {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, header::HeaderValue::from_static("private"));
+    }
+
+    if redirect.varies_by_visitor && visitor_cookie(req).is_none() {
+        if let Some(visitor_id) = &visit.visitor_id {
+            let cookie = Cookie::build(VISITOR_COOKIE, visitor_id.clone())
+                .path(format!("{}/", data.public_url.path_prefix()))
+                .max_age(CookieDuration::days(365))
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .secure(data.public_url.request_scheme(req) == "https")
+                .finish();
+            if let Err(e) = response.add_cookie(&cookie) {
+                tracing::warn!("Failed to set visitor cookie: {}", e);
+            }
+        }
+    }
+    response
+}
+
+/// Temporary redirect used for per-domain fallbacks
+fn fallback_redirect(location: &str) -> HttpResponse {
+    HttpResponse::Found()
+        .insert_header(("Location", location.to_string()))
+        .finish()
+}
+
+/// Check the request's bearer token against the configured admin token
+fn authorize_admin(req: &HttpRequest, data: &AppState) -> Result<(), AppError> {
+    let Some(expected) = data.admin_token.as_deref() else {
+        return Err(AppError::Unauthorized(
+            "admin API is disabled, set ADMIN_TOKEN to enable it".to_string(),
+        ));
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    if constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(AppError::Unauthorized("missing or invalid admin token".to_string()))
+    }
+}
+
+/// Compare secrets without exiting early on the first differing byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Health check endpoint, kept as an alias of the liveness probe
 #[get("/health")]
+#[instrument(name = "route.health_check", skip_all)]
 pub async fn health_check() -> ActixResult<impl Responder> {
-    Ok(HttpResponse::Ok().json(HealthResponse {
-        status: "healthy".to_string(),
-        timestamp: Utc::now(),
-    }))
+    Ok(HttpResponse::Ok().json(health::liveness()))
+}
+
+/// Liveness probe: answers as long as the server is running
+#[get("/health/live")]
+#[instrument(name = "route.liveness_check", skip_all)]
+pub async fn liveness_check() -> ActixResult<impl Responder> {
+    Ok(HttpResponse::Ok().json(health::liveness()))
+}
+
+/// Readiness probe: 503 while Postgres or the schema is unavailable
+#[get("/health/ready")]
+#[instrument(name = "route.readiness_check", skip_all)]
+pub async fn readiness_check(data: web::Data<AppState>) -> ActixResult<impl Responder> {
+    let report = health::readiness(&data.db, &data.cache, data.readiness_timeout).await;
+    if report.status == "unhealthy" {
+        Ok(HttpResponse::ServiceUnavailable().json(report))
+    } else {
+        Ok(HttpResponse::Ok().json(report))
+    }
+}
+
+/// Prometheus metrics endpoint
+#[get("/metrics")]
+#[instrument(name = "route.metrics_endpoint", skip_all)]
+pub async fn metrics_endpoint(data: web::Data<AppState>) -> impl Responder {
+    data.db.record_pool_metrics();
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render())
 }
 
 /// Create a shortened URL
 #[post("/shorten")]
+#[instrument(name = "route.create_short_url", skip_all)]
 pub async fn create_short_url(
+    req: HttpRequest,
     request: web::Json<CreateUrlRequest>,
     data: web::Data<AppState>,
 ) -> ActixResult<impl Responder> {
-    match data.url_service.create_short_url(request.into_inner()).await {
+    let base_url = data.public_url.base_url(&req);
+
+    match data.url_service.create_short_url(request.into_inner(), &base_url).await {
         Ok(response) => Ok(HttpResponse::Created().json(response)),
         Err(e) => Ok(e.error_response()),
     }
 }
 
-/// Redirect to the original URL
-#[get("/{short_code}")]
+/// Redirect the root of a custom domain
+#[get("/")]
+#[instrument(name = "route.redirect_root", skip_all)]
+pub async fn redirect_root(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    match data.url_service.resolve_domain(&data.public_url.request_host(&req)).await {
+        Ok(Some(Domain { root_url: Some(root_url), .. })) => Ok(fallback_redirect(&root_url)),
+        Ok(_) => Ok(AppError::NotFound("No root redirect configured".to_string()).error_response()),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Redirect to the original URL, resolving the domain from the `Host` header;
+/// `HEAD` requests, e.g. from link checkers, get the same redirect
+#[route("/{short_code}", method = "GET", method = "HEAD")]
+#[instrument(name = "route.redirect_to_long_url", skip_all, fields(short_code = %path))]
 pub async fn redirect_to_long_url(
+    req: HttpRequest,
     path: web::Path<String>,
     data: web::Data<AppState>,
 ) -> ActixResult<impl Responder> {
-    let short_code = path.into_inner();
+    Ok(redirect_response(&req, &path.into_inner(), None, &data).await)
+}
+
+/// Redirect a path below the code of a template link, filling its placeholders,
+/// or of a passthrough link, appending it to the destination
+#[route("/{short_code}/{tail:.*}", method = "GET", method = "HEAD")]
+#[instrument(name = "route.redirect_with_path", skip_all, fields(short_code = %path.0))]
+pub async fn redirect_with_path(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    let short_code = path.into_inner().0;
+    // The matched tail is decoded; forward the path as the client encoded it
+    let path_suffix = req
+        .uri()
+        .path()
+        .strip_prefix(data.public_url.path_prefix())
+        .and_then(|path| path.strip_prefix(&format!("/{}/", short_code)))
+        .filter(|suffix| !suffix.is_empty())
+        .map(str::to_string);
+    Ok(redirect_response(&req, &short_code, path_suffix, &data).await)
+}
+
+/// Redirect, or show the preview page of, a visit to a short code on the request's domain
+async fn redirect_response(
+    req: &HttpRequest,
+    short_code: &str,
+    path_suffix: Option<String>,
+    data: &AppState,
+) -> HttpResponse {
+    let domain = match data.url_service.resolve_domain(&data.public_url.request_host(req)).await {
+        Ok(domain) => domain,
+        Err(e) => return e.error_response(),
+    };
+    let domain_key = domain.as_ref().map(|d| d.host.as_str()).unwrap_or_default();
     
-    match data.url_service.get_original_url(&short_code).await {
-        Ok(long_url) => Ok(HttpResponse::MovedPermanently()
-            .insert_header(("Location", long_url))
-            .finish()),
-        Err(e) => Ok(e.error_response()),
+    let visit = visit_context(req, data, path_suffix);
+    let result = match data.url_service.get_original_url(domain_key, short_code, &visit).await {
+        // The click was counted; the visitor continues from the preview page.
+        // Destinations cached before previews were kept need the database
+        Ok(redirect) if redirect.interstitial => {
+            let preview = match &redirect.preview {
+                Some(preview) => Ok(preview.clone()),
+                None => data
+                    .url_service
+                    .preview_url(domain_key, short_code)
+                    .await
+                    .map(|url| LinkPreview::of(&url)),
+            };
+            preview
+                .map(|preview| preview_response(&preview, &redirect.location))
+                .map(|response| vary_response(response, req, data, &visit, &redirect))
+        }
+        Ok(redirect) => {
+            let mut response = if redirect.temporary {
+                HttpResponse::Found()
+            } else {
+                HttpResponse::MovedPermanently()
+            };
+            let response = response.insert_header(("Location", redirect.location.clone())).finish();
+            Ok(vary_response(response, req, data, &visit, &redirect))
+        }
+        Err(e) => Err(e),
+    };
+    result.unwrap_or_else(|e| short_code_error_response(e, domain))
+}
+
+/// Show where a short link goes without redirecting or counting a click
+#[get("/{short_code}+")]
+#[instrument(name = "route.preview_link", skip_all, fields(short_code = %path))]
+pub async fn preview_link(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    let short_code = path.into_inner();
+
+    let domain = match data.url_service.resolve_domain(&data.public_url.request_host(&req)).await {
+        Ok(domain) => domain,
+        Err(e) => return Ok(e.error_response()),
+    };
+    let domain_key = domain.as_ref().map(|d| d.host.as_str()).unwrap_or_default();
+
+    match data.url_service.preview_url(domain_key, &short_code).await {
+        Ok(url) => {
+            let visit = visit_context(&req, &data, None);
+            let destination = Destination::of(&url);
+            // Outside the activation window the fallback is all there is to show,
+            // and templates missing values show the template itself
+            let redirect = match destination.fallback() {
+                Some(fallback) if !destination.is_live(chrono::Utc::now()) => fallback,
+                _ => destination
+                    .resolve(&visit, &link_key(&url.domain, &url.short_code))
+                    .unwrap_or_else(|| Redirect { location: url.long_url.clone(), ..Redirect::default() }),
+            };
+            let response = preview_response(&LinkPreview::of(&url), &redirect.location);
+            Ok(vary_response(response, &req, &data, &visit, &redirect))
+        }
+        Err(e) => Ok(short_code_error_response(e, domain)),
+    }
+}
+
+/// Error response for a short code resolved on `domain`, sending unknown
+/// codes to the domain's not-found page if it has one
+fn short_code_error_response(error: AppError, domain: Option<Domain>) -> HttpResponse {
+    match (error, domain.and_then(|d| d.not_found_url)) {
+        (AppError::NotFound(_), Some(not_found_url)) => fallback_redirect(&not_found_url),
+        (error, _) => error.error_response(),
     }
 }
 
 /// Get URL statistics
 #[get("/stats/{short_code}")]
+#[instrument(name = "route.get_url_stats", skip_all, fields(short_code = %path))]
 pub async fn get_url_stats(
     path: web::Path<String>,
+    query: web::Query<DomainQuery>,
     data: web::Data<AppState>,
 ) -> ActixResult<impl Responder> {
     let short_code = path.into_inner();
     
-    match data.url_service.get_url_stats(&short_code).await {
+    match data.url_service.get_url_stats(&query_domain(&query), &short_code).await {
         Ok(stats) => Ok(HttpResponse::Ok().json(stats)),
         Err(e) => Ok(e.error_response()),
     }
 }
 
-/// Delete a shortened URL
+/// Clicks over time in hourly, daily or weekly buckets, with breakdowns
+#[get("/stats/{short_code}/timeseries")]
+#[instrument(name = "route.get_url_timeseries", skip_all, fields(short_code = %path))]
+pub async fn get_url_timeseries(
+    path: web::Path<String>,
+    query: web::Query<TimeseriesQuery>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    let short_code = path.into_inner();
+    let query = query.into_inner();
+    let domain = query.domain.as_deref().map(normalize_host).unwrap_or_default();
+
+    match data
+        .url_service
+        .get_url_timeseries(&domain, &short_code, query.interval, query.from, query.to)
+        .await
+    {
+        Ok(timeseries) => Ok(HttpResponse::Ok().json(timeseries)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Check whether a custom code is free, suggesting alternatives if taken
+#[get("/codes/{code}/availability")]
+#[instrument(name = "route.check_code_availability", skip_all, fields(code = %path))]
+pub async fn check_code_availability(
+    path: web::Path<String>,
+    query: web::Query<DomainQuery>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    let code = path.into_inner();
+
+    match data
+        .url_service
+        .check_code_availability(query.domain.as_deref(), &code)
+        .await
+    {
+        Ok(availability) => Ok(HttpResponse::Ok().json(availability)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Delete a shortened URL; the code stays reserved and the link can be restored
 #[delete("/{short_code}")]
+#[instrument(name = "route.delete_short_url", skip_all, fields(short_code = %path))]
 pub async fn delete_short_url(
     path: web::Path<String>,
+    query: web::Query<DomainQuery>,
     data: web::Data<AppState>,
 ) -> ActixResult<impl Responder> {
     let short_code = path.into_inner();
     
-    match data.url_service.delete_url(&short_code).await {
+    match data.url_service.delete_url(&query_domain(&query), &short_code).await {
         Ok(deleted) => {
             if deleted {
                 Ok(HttpResponse::NoContent().finish())
@@ -108,6 +489,8 @@ pub async fn delete_short_url(
                     error: "Short code not found".to_string(),
                     message: "The specified short code does not exist".to_string(),
                     code: 404,
+                    suggestions: None,
+                    request_id: request_id::current(),
                 }))
             }
         }
@@ -115,13 +498,222 @@ pub async fn delete_short_url(
     }
 }
 
+/// Register a custom domain; its root and not-found redirects make this an admin operation
+#[post("/domains")]
+#[instrument(name = "route.create_domain", skip_all)]
+pub async fn create_domain(
+    req: HttpRequest,
+    request: web::Json<CreateDomainRequest>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    if let Err(e) = authorize_admin(&req, &data) {
+        return Ok(e.error_response());
+    }
+
+    match data.url_service.create_domain(request.into_inner()).await {
+        Ok(domain) => Ok(HttpResponse::Created().json(domain)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// List registered custom domains
+#[get("/domains")]
+#[instrument(name = "route.list_domains", skip_all)]
+pub async fn list_domains(req: HttpRequest, data: web::Data<AppState>) -> ActixResult<impl Responder> {
+    if let Err(e) = authorize_admin(&req, &data) {
+        return Ok(e.error_response());
+    }
+
+    match data.url_service.list_domains().await {
+        Ok(domains) => Ok(HttpResponse::Ok().json(domains)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// List links, newest first, filtered by domain, tags or text
+#[get("/admin/links")]
+#[instrument(name = "route.list_links", skip_all)]
+pub async fn list_links(
+    req: HttpRequest,
+    query: web::Query<LinkListQuery>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    if let Err(e) = authorize_admin(&req, &data) {
+        return Ok(e.error_response());
+    }
+
+    match data.url_service.list_links(query.into_inner()).await {
+        Ok(page) => Ok(HttpResponse::Ok().json(page)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Restore a deleted link while its code is still reserved
+#[post("/admin/links/{short_code}/restore")]
+#[instrument(name = "route.restore_link", skip_all, fields(short_code = %path))]
+pub async fn restore_link(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<DomainQuery>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    if let Err(e) = authorize_admin(&req, &data) {
+        return Ok(e.error_response());
+    }
+
+    match data.url_service.restore_url(&query_domain(&query), &path).await {
+        Ok(url) => Ok(HttpResponse::Ok().json(url)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Disable a link; it answers 410 Gone until enabled again
+#[post("/admin/links/{short_code}/disable")]
+#[instrument(name = "route.disable_link", skip_all, fields(short_code = %path))]
+pub async fn disable_link(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<DomainQuery>,
+    request: Option<web::Json<DisableUrlRequest>>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    if let Err(e) = authorize_admin(&req, &data) {
+        return Ok(e.error_response());
+    }
+
+    let reason = request
+        .and_then(|request| request.into_inner().reason)
+        .filter(|reason| !reason.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_DISABLED_REASON.to_string());
+    match data
+        .url_service
+        .set_disabled(&query_domain(&query), &path, Some(reason))
+        .await
+    {
+        Ok(url) => Ok(HttpResponse::Ok().json(url)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Re-enable a disabled link
+#[post("/admin/links/{short_code}/enable")]
+#[instrument(name = "route.enable_link", skip_all, fields(short_code = %path))]
+pub async fn enable_link(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<DomainQuery>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    if let Err(e) = authorize_admin(&req, &data) {
+        return Ok(e.error_response());
+    }
+
+    match data.url_service.set_disabled(&query_domain(&query), &path, None).await {
+        Ok(url) => Ok(HttpResponse::Ok().json(url)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Bulk import links from a CSV or JSONL request body
+#[post("/admin/links/import")]
+#[instrument(name = "route.import_links", skip_all)]
+pub async fn import_links(
+    req: HttpRequest,
+    mut payload: web::Payload,
+    query: web::Query<ImportOptions>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    if let Err(e) = authorize_admin(&req, &data) {
+        return Ok(e.error_response());
+    }
+
+    // The body is parsed as it arrives; forwarding stops once the parser is done with it
+    let (chunks, received) = mpsc::channel(8);
+    let forward = async move {
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk.map_err(io::Error::other);
+            let failed = chunk.is_err();
+            if chunks.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+    };
+    let input = Box::new(ChannelReader::new(received));
+    let (_, report) = tokio::join!(forward, data.url_service.import_links(input, query.into_inner()));
+
+    match report {
+        Ok(report) => Ok(HttpResponse::Ok().json(report)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Export all links as CSV or JSONL
+#[get("/admin/links/export")]
+#[instrument(name = "route.export_links", skip_all)]
+pub async fn export_links(
+    req: HttpRequest,
+    query: web::Query<ExportOptions>,
+    data: web::Data<AppState>,
+) -> ActixResult<impl Responder> {
+    if let Err(e) = authorize_admin(&req, &data) {
+        return Ok(e.error_response());
+    }
+
+    let format = query.format;
+    match data.url_service.export_links(format).await {
+        Ok(stream) => Ok(HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"links.{}\"",
+                    match format {
+                        TransferFormat::Csv => "csv",
+                        TransferFormat::Jsonl => "jsonl",
+                    }
+                ),
+            ))
+            .streaming(stream)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// First path segments of the fixed routes, which take priority over short
+/// codes; links with these codes could never be reached
+pub const RESERVED_CODES: &[&str] = &[
+    "admin", "api-docs", "codes", "domains", "health", "metrics", "shorten", "stats", "swagger-ui",
+];
+
+/// Whether `code` collides with a fixed route, regardless of case
+pub fn is_reserved_code(code: &str) -> bool {
+    RESERVED_CODES.iter().any(|reserved| reserved.eq_ignore_ascii_case(code))
+}
+
 /// Configure all routes
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(health_check)
+        .service(liveness_check)
+        .service(readiness_check)
+        .service(metrics_endpoint)
         .service(create_short_url)
+        .service(create_domain)
+        .service(list_domains)
+        .service(list_links)
+        .service(import_links)
+        .service(export_links)
+        .service(restore_link)
+        .service(disable_link)
+        .service(enable_link)
+        .service(redirect_root)
+        // Before the redirect, whose pattern also matches codes ending in '+'
+        .service(preview_link)
         .service(redirect_to_long_url)
+        .service(get_url_timeseries)
         .service(get_url_stats)
-        .service(delete_short_url);
+        .service(check_code_availability)
+        .service(delete_short_url)
+        // Last, as it matches any path of two or more segments
+        .service(redirect_with_path);
 }
 
 /// Legacy route configuration for backward compatibility
@@ -1,10 +1,15 @@
+use crate::core::metrics;
 use crate::models::{AppError, Result};
 use crate::traits::CacheService;
 use async_trait::async_trait;
 use dashmap::DashMap;
+use hyperloglogplus::{HyperLogLog, HyperLogLogPlus};
+use once_cell::sync::Lazy;
 use redis::{AsyncCommands, Client};
+use std::collections::hash_map::RandomState;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tracing::instrument;
 
 /// Cache entry for in-memory fallback
 #[derive(Clone)]
@@ -13,20 +18,63 @@ struct CacheEntry {
     expires_at: Instant,
 }
 
+/// Precision of in-memory distinct counts, the one Redis uses: 2^14 registers,
+/// about 0.8% standard error
+const UNIQUE_PRECISION: u8 = 14;
+
+/// `PFADD` that sets the key's expiry (`ARGV[2]` seconds, 0 for none) only
+/// while it has none, so it runs from the first add; works on any Redis with
+/// HyperLogLogs, unlike `EXPIRE ... NX`
+static ADD_UNIQUE_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        for i, key in ipairs(KEYS) do
+            redis.call('PFADD', key, ARGV[1])
+            local ttl = tonumber(ARGV[i + 1])
+            if ttl > 0 and redis.call('TTL', key) == -1 then
+                redis.call('EXPIRE', key, ttl)
+            end
+        end
+        return 1
+        "#,
+    )
+});
+
+/// Distinct count for in-memory fallback
+struct UniqueEntry {
+    counter: HyperLogLogPlus<str, RandomState>,
+    expires_at: Option<Instant>,
+}
+
+impl UniqueEntry {
+    fn new(now: Instant, ttl_seconds: Option<u64>) -> Self {
+        Self {
+            counter: HyperLogLogPlus::new(UNIQUE_PRECISION, RandomState::new())
+                .expect("precision is within bounds"),
+            expires_at: ttl_seconds.map(|ttl_seconds| now + Duration::from_secs(ttl_seconds)),
+        }
+    }
+
+    fn is_live(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
 /// High-performance cache service with Redis and in-memory fallback
 pub struct RedisCacheService {
     redis_client: Option<Client>,
     fallback_cache: Arc<DashMap<String, CacheEntry>>,
+    fallback_uniques: Arc<DashMap<String, UniqueEntry>>,
     default_ttl: Duration,
 }
 
 impl RedisCacheService {
-    pub fn new(redis_url: Option<String>) -> Result<Self> {
+    pub fn new(redis_url: Option<String>, default_ttl_seconds: u64) -> Result<Self> {
         let redis_client = if let Some(url) = redis_url {
             match Client::open(url) {
                 Ok(client) => Some(client),
                 Err(e) => {
-                    log::warn!("Failed to connect to Redis: {}, using in-memory cache", e);
+                    tracing::warn!("Failed to connect to Redis: {}, using in-memory cache", e);
                     None
                 }
             }
@@ -37,7 +85,8 @@ impl RedisCacheService {
         Ok(Self {
             redis_client,
             fallback_cache: Arc::new(DashMap::new()),
-            default_ttl: Duration::from_secs(3600), // 1 hour default
+            fallback_uniques: Arc::new(DashMap::new()),
+            default_ttl: Duration::from_secs(default_ttl_seconds),
         })
     }
 
@@ -47,7 +96,32 @@ impl RedisCacheService {
         self.fallback_cache.retain(|_, entry| entry.expires_at > now);
     }
 
+    /// Count an operation served by the in-memory cache although Redis is configured
+    fn record_fallback(&self, operation: &str) {
+        if self.redis_client.is_some() {
+            metrics::CACHE_FALLBACKS.with_label_values(&[operation]).inc();
+        }
+    }
+
+    /// Whether a Redis server is configured, rather than only the in-memory cache
+    pub fn has_redis(&self) -> bool {
+        self.redis_client.is_some()
+    }
+
+    /// Round-trip a `PING` to the configured Redis server
+    #[instrument(name = "redis.ping", skip(self))]
+    pub async fn ping(&self) -> Result<()> {
+        let client = self
+            .redis_client
+            .as_ref()
+            .ok_or_else(|| AppError::Internal("Redis is not configured".to_string()))?;
+        let mut conn = client.get_async_connection().await?;
+        redis::cmd("PING").query_async::<_, String>(&mut conn).await?;
+        Ok(())
+    }
+
     /// Get Redis connection
+    #[instrument(name = "redis.connect", skip(self), fields(configured = self.redis_client.is_some()))]
     async fn get_redis_connection(&self) -> Option<redis::aio::Connection> {
         match &self.redis_client {
             Some(client) => client.get_async_connection().await.ok(),
@@ -58,40 +132,50 @@ impl RedisCacheService {
 
 #[async_trait]
 impl CacheService for RedisCacheService {
+    #[instrument(name = "cache.get", skip(self))]
     async fn get(&self, key: &str) -> Result<Option<String>> {
         // Try Redis first
         if let Some(mut conn) = self.get_redis_connection().await {
             match conn.get::<_, Option<String>>(key).await {
-                Ok(value) => return Ok(value),
-                Err(e) => log::warn!("Redis get error: {}", e),
+                Ok(value) => {
+                    let result = if value.is_some() { "hit" } else { "miss" };
+                    metrics::CACHE_LOOKUPS.with_label_values(&[result]).inc();
+                    return Ok(value);
+                }
+                Err(e) => tracing::warn!("Redis get error: {}", e),
             }
         }
 
         // Fallback to in-memory cache
+        self.record_fallback("get");
         self.cleanup_expired();
         
         if let Some(entry) = self.fallback_cache.get(key) {
             if entry.expires_at > Instant::now() {
+                metrics::CACHE_LOOKUPS.with_label_values(&["hit"]).inc();
                 return Ok(Some(entry.value.clone()));
-            } else {
-                // Remove expired entry
-                self.fallback_cache.remove(key);
             }
         }
 
+        // Remove expired entry, if any
+        self.fallback_cache.remove(key);
+        metrics::CACHE_LOOKUPS.with_label_values(&["miss"]).inc();
+
         Ok(None)
     }
 
+    #[instrument(name = "cache.set", skip(self, value))]
     async fn set(&self, key: &str, value: &str, ttl_seconds: u64) -> Result<()> {
         // Try Redis first
         if let Some(mut conn) = self.get_redis_connection().await {
             match conn.set_ex::<_, _, ()>(key, value, ttl_seconds).await {
                 Ok(_) => return Ok(()),
-                Err(e) => log::warn!("Redis set error: {}", e),
+                Err(e) => tracing::warn!("Redis set error: {}", e),
             }
         }
 
         // Fallback to in-memory cache
+        self.record_fallback("set");
         let expires_at = Instant::now() + Duration::from_secs(ttl_seconds);
         self.fallback_cache.insert(
             key.to_string(),
@@ -104,21 +188,24 @@ impl CacheService for RedisCacheService {
         Ok(())
     }
 
+    #[instrument(name = "cache.delete", skip(self))]
     async fn delete(&self, key: &str) -> Result<()> {
         // Try Redis first
         if let Some(mut conn) = self.get_redis_connection().await {
             match conn.del::<_, ()>(key).await {
                 Ok(_) => {},
-                Err(e) => log::warn!("Redis delete error: {}", e),
+                Err(e) => tracing::warn!("Redis delete error: {}", e),
             }
         }
 
         // Also remove from fallback cache
         self.fallback_cache.remove(key);
+        self.fallback_uniques.remove(key);
 
         Ok(())
     }
 
+    #[instrument(name = "cache.increment_clicks", skip(self))]
     async fn increment_clicks(&self, short_code: &str) -> Result<i64> {
         let clicks_key = format!("clicks:{}", short_code);
         
@@ -126,11 +213,12 @@ impl CacheService for RedisCacheService {
         if let Some(mut conn) = self.get_redis_connection().await {
             match conn.incr::<_, _, i64>(&clicks_key, 1).await {
                 Ok(count) => return Ok(count),
-                Err(e) => log::warn!("Redis increment error: {}", e),
+                Err(e) => tracing::warn!("Redis increment error: {}", e),
             }
         }
 
         // Fallback to in-memory cache
+        self.record_fallback("increment");
         let mut entry = self.fallback_cache.entry(clicks_key.clone()).or_insert_with(|| {
             CacheEntry {
                 value: "0".to_string(),
@@ -144,4 +232,97 @@ impl CacheService for RedisCacheService {
 
         Ok(new_count)
     }
-} 
\ No newline at end of file
+
+    #[instrument(name = "cache.add_unique", skip(self, member))]
+    async fn add_uniques(&self, counts: &[(&str, Option<u64>)], member: &str) -> Result<()> {
+        // Try Redis first; the adds and the expiries run as one script, so a key is
+        // never left without its expiry
+        if let Some(mut conn) = self.get_redis_connection().await {
+            let mut invocation = ADD_UNIQUE_SCRIPT.arg(member);
+            for (key, ttl_seconds) in counts {
+                invocation.key(*key).arg(ttl_seconds.unwrap_or(0));
+            }
+            match invocation.invoke_async::<_, ()>(&mut conn).await {
+                Ok(()) => return Ok(()),
+                Err(e) => tracing::warn!("Redis pfadd error: {}", e),
+            }
+        }
+
+        // Fallback to in-memory counters, which likewise expire from their first add
+        self.record_fallback("add_unique");
+        let now = Instant::now();
+        for &(key, ttl_seconds) in counts {
+            if !self.fallback_uniques.contains_key(key) {
+                self.fallback_uniques.retain(|_, entry| entry.is_live(now));
+            }
+            let mut entry = self
+                .fallback_uniques
+                .entry(key.to_string())
+                .or_insert_with(|| UniqueEntry::new(now, ttl_seconds));
+            if !entry.is_live(now) {
+                *entry = UniqueEntry::new(now, ttl_seconds);
+            }
+            entry.counter.insert(member);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(name = "cache.count_unique", skip(self))]
+    async fn count_unique(&self, key: &str) -> Result<u64> {
+        // Try Redis first
+        if let Some(mut conn) = self.get_redis_connection().await {
+            match conn.pfcount::<_, u64>(key).await {
+                Ok(count) => return Ok(count),
+                Err(e) => tracing::warn!("Redis pfcount error: {}", e),
+            }
+        }
+
+        // Fallback to in-memory counters
+        self.record_fallback("count_unique");
+        let count = match self.fallback_uniques.get_mut(key) {
+            Some(mut entry) if entry.is_live(Instant::now()) => entry.counter.count().round() as u64,
+            _ => 0,
+        };
+
+        Ok(count)
+    }
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fallback_uniques_count_distinct_members() {
+        let cache = RedisCacheService::new(None, 60).unwrap();
+        for i in 0..1000 {
+            cache.add_unique("uniques", &format!("visitor-{}", i % 250), None).await.unwrap();
+        }
+
+        let count = cache.count_unique("uniques").await.unwrap();
+        assert!((245..=255).contains(&count), "expected about 250, got {}", count);
+        assert_eq!(cache.count_unique("other").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn fallback_uniques_expire_from_the_first_add() {
+        let cache = RedisCacheService::new(None, 60).unwrap();
+        cache.add_unique("daily", "a", Some(0)).await.unwrap();
+        assert_eq!(cache.count_unique("daily").await.unwrap(), 0);
+
+        // An expired counter starts over rather than collecting members nobody can read
+        cache.add_unique("daily", "b", Some(60)).await.unwrap();
+        cache.add_unique("daily", "c", Some(0)).await.unwrap();
+        assert_eq!(cache.count_unique("daily").await.unwrap(), 2);
+        let expires_at = cache.fallback_uniques.get("daily").unwrap().expires_at.unwrap();
+        assert!(expires_at > Instant::now() + Duration::from_secs(50));
+    }
+
+    #[tokio::test]
+    async fn deleting_a_key_drops_its_fallback_uniques() {
+        let cache = RedisCacheService::new(None, 60).unwrap();
+        cache.add_unique("uniques", "a", None).await.unwrap();
+        cache.delete("uniques").await.unwrap();
+        assert_eq!(cache.count_unique("uniques").await.unwrap(), 0);
+    }
+}
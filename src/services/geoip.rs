@@ -0,0 +1,125 @@
+use crate::models::{AppError, Result};
+use crate::traits::GeoLocator;
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Country lookups in a local MaxMind-format database
+///
+/// Works with GeoIP2/GeoLite2 Country and City databases and other files
+/// using their `country.iso_code` layout. The file is read into memory once;
+/// replacing it takes a restart.
+pub struct MaxMindGeoLocator {
+    reader: Reader<Vec<u8>>,
+}
+
+impl MaxMindGeoLocator {
+    pub fn open(path: &Path) -> Result<Self> {
+        let reader = Reader::open_readfile(path).map_err(|e| {
+            AppError::Internal(format!("Failed to open GeoIP database '{}': {}", path.display(), e))
+        })?;
+        Ok(Self { reader })
+    }
+
+    /// Database type and build date, for the startup log
+    pub fn describe(&self) -> String {
+        let built = chrono::DateTime::from_timestamp(self.reader.metadata.build_epoch as i64, 0)
+            .map(|built| built.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        format!("{} built {}", self.reader.metadata.database_type, built)
+    }
+}
+
+impl GeoLocator for MaxMindGeoLocator {
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        // Lookups of IPv4-mapped peers work in IPv4-only databases too
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+
+        match self.reader.lookup::<geoip2::Country>(ip) {
+            Ok(record) => record
+                .country
+                .or(record.registered_country)
+                .and_then(|country| country.iso_code)
+                .map(str::to_ascii_uppercase),
+            Err(MaxMindDBError::AddressNotFoundError(_)) => None,
+            Err(e) => {
+                tracing::warn!("GeoIP lookup of {} failed: {}", ip, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{validate_redirect_rules, RedirectRule, VisitContext};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    /// Written by `tests/data/make_test_country_mmdb.py`: 127.0.0.0/8 is DE,
+    /// 10.0.0.0/8 US and 192.0.2.0/24 FR
+    fn locator() -> MaxMindGeoLocator {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/test-country.mmdb");
+        MaxMindGeoLocator::open(&path).unwrap()
+    }
+
+    #[test]
+    fn looks_up_countries() {
+        let locator = locator();
+        assert_eq!(locator.country(Ipv4Addr::new(127, 0, 0, 1).into()).as_deref(), Some("DE"));
+        assert_eq!(locator.country(Ipv4Addr::new(10, 1, 2, 3).into()).as_deref(), Some("US"));
+        assert_eq!(locator.country(Ipv4Addr::new(192, 0, 2, 200).into()).as_deref(), Some("FR"));
+        assert_eq!(locator.country(Ipv4Addr::new(198, 51, 100, 1).into()), None);
+    }
+
+    #[test]
+    fn looks_up_ipv4_mapped_addresses() {
+        let mapped = Ipv4Addr::new(10, 0, 0, 1).to_ipv6_mapped();
+        assert_eq!(locator().country(mapped.into()).as_deref(), Some("US"));
+        assert_eq!(locator().country(Ipv6Addr::LOCALHOST.into()), None);
+    }
+
+    #[test]
+    fn describes_the_database() {
+        assert_eq!(locator().describe(), "Test-Country built 2026-01-01");
+    }
+
+    #[test]
+    fn missing_database_is_an_error() {
+        assert!(MaxMindGeoLocator::open(Path::new("tests/data/missing.mmdb")).is_err());
+    }
+
+    #[test]
+    fn looked_up_country_picks_the_country_rule() {
+        let rule = RedirectRule {
+            os: Vec::new(),
+            device: Vec::new(),
+            browser: Vec::new(),
+            country: vec!["de".to_string(), "AT".to_string()],
+            url: "https://example.de".to_string(),
+        };
+        let visit = |ip: Ipv4Addr| VisitContext::default().with_country(locator().country(ip.into()));
+
+        assert!(rule.matches(&visit(Ipv4Addr::new(127, 0, 0, 1))));
+        assert!(!rule.matches(&visit(Ipv4Addr::new(10, 0, 0, 1))));
+        assert!(!rule.matches(&visit(Ipv4Addr::new(198, 51, 100, 1))));
+    }
+
+    #[test]
+    fn country_rules_need_web_urls() {
+        let rule = |url: &str| RedirectRule {
+            os: Vec::new(),
+            device: Vec::new(),
+            browser: Vec::new(),
+            country: vec!["DE".to_string()],
+            url: url.to_string(),
+        };
+
+        assert!(validate_redirect_rules(&[rule("https://example.de")]).is_ok());
+        assert!(validate_redirect_rules(&[rule("javascript:alert(1)")]).is_err());
+        assert!(validate_redirect_rules(&[rule("data:text/html,hi")]).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::models::{
+    AppError, ImportBatch, ImportIssue, ImportRow, LinkRecord, Result, TransferFormat,
+    MAX_IMPORT_ISSUES,
+};
+use bytes::{Buf, Bytes};
+use chrono::Utc;
+use std::io::{self, BufRead, BufReader, Read};
+use tokio::sync::mpsc;
+
+/// Rows sent to the repository per batch
+const IMPORT_BATCH_SIZE: usize = 1000;
+
+/// Parser-side totals of an import
+#[derive(Debug, Default)]
+pub struct ParseSummary {
+    pub rows_read: u64,
+    pub invalid: u64,
+    pub issues: Vec<ImportIssue>,
+}
+
+impl ParseSummary {
+    fn reject(&mut self, line: u64, short_code: Option<String>, message: String) {
+        self.invalid += 1;
+        if self.issues.len() < MAX_IMPORT_ISSUES {
+            self.issues.push(ImportIssue {
+                line,
+                short_code,
+                message,
+            });
+        }
+    }
+}
+
+/// Read links from `input`, sending valid rows to `batches` and collecting the
+/// invalid ones. Blocking; run it off the async runtime.
+///
+/// Returns early without error if the receiver goes away, since the receiver
+/// reports its own failure.
+pub fn parse_links(
+    input: impl Read,
+    format: TransferFormat,
+    batches: mpsc::Sender<ImportBatch>,
+) -> Result<ParseSummary> {
+    let mut parser = BatchingParser {
+        summary: ParseSummary::default(),
+        batch: Vec::with_capacity(IMPORT_BATCH_SIZE),
+        batches,
+        closed: false,
+    };
+
+    match format {
+        TransferFormat::Csv => parser.read_csv(input)?,
+        TransferFormat::Jsonl => parser.read_jsonl(input)?,
+    }
+
+    parser.flush();
+    if !parser.closed {
+        let _ = parser.batches.blocking_send(ImportBatch::End);
+    }
+    Ok(parser.summary)
+}
+
+struct BatchingParser {
+    summary: ParseSummary,
+    batch: Vec<ImportRow>,
+    batches: mpsc::Sender<ImportBatch>,
+    /// Set once the receiver has gone away
+    closed: bool,
+}
+
+impl BatchingParser {
+    fn read_csv(&mut self, input: impl Read) -> Result<()> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_reader(input);
+        let headers = reader.headers().map_err(csv_input_error)?.clone();
+        for required in ["short_code", "long_url"] {
+            if !headers.iter().any(|header| header == required) {
+                return Err(AppError::Validation(format!(
+                    "CSV header must include a '{}' column",
+                    required
+                )));
+            }
+        }
+
+        let mut record = csv::StringRecord::new();
+        while !self.closed {
+            match reader.read_record(&mut record) {
+                Ok(false) => break,
+                Ok(true) => {
+                    let line = record.position().map(|p| p.line()).unwrap_or_default();
+                    match record.deserialize::<LinkRecord>(Some(&headers)) {
+                        Ok(link) => self.accept(line, link),
+                        Err(e) => {
+                            self.summary.rows_read += 1;
+                            self.summary.reject(line, None, e.to_string());
+                        }
+                    }
+                }
+                Err(e) if e.is_io_error() => return Err(csv_input_error(e)),
+                Err(e) => {
+                    let line = e.position().map(|p| p.line()).unwrap_or_default();
+                    self.summary.rows_read += 1;
+                    self.summary.reject(line, None, e.to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_jsonl(&mut self, input: impl Read) -> Result<()> {
+        for (index, line) in BufReader::new(input).lines().enumerate() {
+            if self.closed {
+                break;
+            }
+            let line_number = index as u64 + 1;
+            let line = line.map_err(|e| AppError::Validation(format!("Failed to read input: {}", e)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<LinkRecord>(&line) {
+                Ok(link) => self.accept(line_number, link),
+                Err(e) => {
+                    self.summary.rows_read += 1;
+                    self.summary.reject(line_number, None, e.to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn accept(&mut self, line: u64, link: LinkRecord) {
+        self.summary.rows_read += 1;
+        let short_code = link.short_code.clone();
+        match link.into_url(Utc::now()) {
+            Ok(url) => {
+                self.batch.push(ImportRow { line, url });
+                if self.batch.len() >= IMPORT_BATCH_SIZE {
+                    self.flush();
+                }
+            }
+            Err(message) => self.summary.reject(line, Some(short_code), message),
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.batch.is_empty() || self.closed {
+            return;
+        }
+        let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(IMPORT_BATCH_SIZE));
+        if self.batches.blocking_send(ImportBatch::Rows(batch)).is_err() {
+            self.closed = true;
+        }
+    }
+}
+
+fn csv_input_error(e: csv::Error) -> AppError {
+    AppError::Validation(format!("Invalid CSV input: {}", e))
+}
+
+/// Blocking `Read` over chunks from an async producer, such as a request body
+/// forwarded by its handler
+pub struct ChannelReader {
+    chunks: mpsc::Receiver<io::Result<Bytes>>,
+    current: Bytes,
+}
+
+impl ChannelReader {
+    pub fn new(chunks: mpsc::Receiver<io::Result<Bytes>>) -> Self {
+        Self {
+            chunks,
+            current: Bytes::new(),
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.current.is_empty() {
+            match self.chunks.blocking_recv() {
+                Some(Ok(chunk)) => self.current = chunk,
+                Some(Err(e)) => return Err(e),
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.current.len());
+        buf[..len].copy_from_slice(&self.current[..len]);
+        self.current.advance(len);
+        Ok(len)
+    }
+}
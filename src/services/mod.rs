@@ -1,7 +1,13 @@
 pub mod url_service;
 pub mod cache_service;
 pub mod short_code_generator;
+pub mod link_transfer;
+pub mod page_fetcher;
+pub mod geoip;
 
 pub use url_service::*;
 pub use cache_service::*;
-pub use short_code_generator::*; 
\ No newline at end of file
+pub use short_code_generator::*;
+pub use link_transfer::*;
+pub use page_fetcher::*;
+pub use geoip::*; 
\ No newline at end of file
@@ -0,0 +1,732 @@
+use crate::core::config::PageFetchConfig;
+use crate::core::metrics;
+use crate::models::{
+    AppError, PageMetadata, Result, MAX_DESCRIPTION_LENGTH, MAX_TITLE_LENGTH,
+};
+use crate::traits::PageFetcher;
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, LOCATION};
+use reqwest::redirect::Policy;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::instrument;
+use url::{Host, Url};
+
+/// Longest image or favicon URL kept
+const MAX_ASSET_URL_LENGTH: usize = 2048;
+
+/// Fetches destination pages over HTTP(S) and reads their `<head>`
+///
+/// Only public addresses are contacted unless private networks are allowed:
+/// host names are checked after resolution, so a public name pointing at an
+/// internal address is refused as well. Redirects are followed by hand so
+/// every hop goes through the same checks.
+pub struct HttpPageFetcher {
+    client: reqwest::Client,
+    timeout: Duration,
+    max_bytes: usize,
+    max_redirects: usize,
+    allow_private_networks: bool,
+}
+
+impl HttpPageFetcher {
+    pub fn new(config: &PageFetchConfig) -> Result<Self> {
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let client = reqwest::Client::builder()
+            .redirect(Policy::none())
+            // A proxy would resolve names where the address checks cannot see
+            .no_proxy()
+            .timeout(timeout)
+            .user_agent(&config.user_agent)
+            .dns_resolver(Arc::new(PublicResolver {
+                allow_private_networks: config.allow_private_networks,
+            }))
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            timeout,
+            max_bytes: config.max_bytes,
+            max_redirects: config.max_redirects,
+            allow_private_networks: config.allow_private_networks,
+        })
+    }
+
+    async fn fetch_page(&self, url: &str) -> std::result::Result<PageMetadata, String> {
+        let mut url = Url::parse(url).map_err(|e| format!("invalid URL: {}", e))?;
+        let mut redirects = 0;
+
+        let mut response = loop {
+            self.check_destination(&url)?;
+            let response = self
+                .client
+                .get(url.clone())
+                .header(ACCEPT, "text/html,application/xhtml+xml;q=0.9,*/*;q=0.1")
+                .send()
+                .await
+                .map_err(describe_error)?;
+
+            if !matches!(response.status().as_u16(), 301 | 302 | 303 | 307 | 308) {
+                break response;
+            }
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| format!("HTTP {} without a Location header", response.status()))?;
+            if redirects == self.max_redirects {
+                return Err(format!("more than {} redirects", self.max_redirects));
+            }
+            redirects += 1;
+            url = url
+                .join(location)
+                .map_err(|e| format!("invalid redirect to '{}': {}", location, e))?;
+        };
+
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+
+        let is_html = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_none_or(|content_type| content_type.to_ascii_lowercase().contains("html"));
+        if !is_html {
+            return Ok(PageMetadata {
+                favicon: resolve_asset(&url, "/favicon.ico"),
+                final_url: Some(url.to_string()),
+                fetched_at: Utc::now(),
+                ..Default::default()
+            });
+        }
+
+        // Metadata lives in the <head>, so stop reading once it is over
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(describe_error)? {
+            let searched_up_to = body.len().saturating_sub(b"</head".len());
+            let room = self.max_bytes - body.len();
+            body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+            if body.len() >= self.max_bytes
+                || find_ignore_case(&body[searched_up_to..], b"</head").is_some()
+            {
+                break;
+            }
+        }
+
+        Ok(extract_page_metadata(&String::from_utf8_lossy(&body), &url))
+    }
+
+    /// Refuse non-HTTP schemes and literal addresses that are not public;
+    /// host names are checked by the resolver
+    fn check_destination(&self, url: &Url) -> std::result::Result<(), String> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("unsupported scheme '{}'", url.scheme()));
+        }
+        let ip = match url.host() {
+            Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+            Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+            Some(Host::Domain(_)) => return Ok(()),
+            None => return Err("URL has no host".to_string()),
+        };
+        if self.allow_private_networks || is_public_ip(ip) {
+            Ok(())
+        } else {
+            Err(format!("{} is not a public address", ip))
+        }
+    }
+}
+
+#[async_trait]
+impl PageFetcher for HttpPageFetcher {
+    #[instrument(name = "page_fetch", skip(self))]
+    async fn fetch(&self, url: &str) -> PageMetadata {
+        let result = tokio::time::timeout(self.timeout, self.fetch_page(url))
+            .await
+            .unwrap_or_else(|_| Err(format!("timed out after {}ms", self.timeout.as_millis())));
+
+        match result {
+            Ok(metadata) => {
+                metrics::PAGE_FETCHES.with_label_values(&["ok"]).inc();
+                metadata
+            }
+            Err(error) => {
+                tracing::info!("Could not fetch page metadata: {}", error);
+                metrics::PAGE_FETCHES.with_label_values(&["error"]).inc();
+                PageMetadata {
+                    fetched_at: Utc::now(),
+                    error: Some(error),
+                    ..Default::default()
+                }
+            }
+        }
+    }
+}
+
+/// System resolver that drops addresses outside the public internet
+struct PublicResolver {
+    allow_private_networks: bool,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_private_networks = self.allow_private_networks;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| allow_private_networks || is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether an address is routable on the public internet
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking
+        || (a == 198 && (18..20).contains(&b))
+        // Reserved
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local
+        || (segments[0] & 0xfe00) == 0xfc00
+        // Link-local
+        || (segments[0] & 0xffc0) == 0xfe80
+        // Documentation
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+        // NAT64, which can reach any IPv4 address
+        || (segments[0] == 0x0064 && segments[1] == 0xff9b))
+}
+
+/// A reqwest error with its causes, which carry the useful detail
+fn describe_error(error: reqwest::Error) -> String {
+    if error.is_timeout() {
+        return "timed out".to_string();
+    }
+    let error = error.without_url();
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(&error);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+/// Pull the title, Open Graph tags and favicon out of a page's `<head>`
+fn extract_page_metadata(html: &str, page_url: &Url) -> PageMetadata {
+    let mut base_url = page_url.clone();
+    let mut title = None;
+    // First value of each `<meta>` property or name, lowercased
+    let mut properties: HashMap<String, String> = HashMap::new();
+    // Favicon candidates with a rank, lowest preferred
+    let mut icons: Vec<(u8, String)> = Vec::new();
+
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if rest.get(..5).is_some_and(|tag| tag.eq_ignore_ascii_case("/head")) {
+            break;
+        }
+
+        let name_end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        let (attributes, after) = parse_attributes(&rest[name_end..]);
+        rest = after;
+        let attribute = |key: &str| {
+            attributes
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.as_str())
+        };
+
+        match name.as_str() {
+            "title" => {
+                let end = find_ignore_case(rest.as_bytes(), b"</title").unwrap_or(rest.len());
+                title = title.or_else(|| Some(decode_entities(&rest[..end])));
+                rest = &rest[end..];
+            }
+            "meta" => {
+                let key = attribute("property").or_else(|| attribute("name"));
+                if let (Some(key), Some(content)) = (key, attribute("content")) {
+                    properties
+                        .entry(key.to_ascii_lowercase())
+                        .or_insert_with(|| content.to_string());
+                }
+            }
+            "link" => {
+                let rel = attribute("rel").unwrap_or_default().to_ascii_lowercase();
+                let rank = match rel.split_ascii_whitespace().collect::<Vec<_>>().as_slice() {
+                    ["icon"] | ["shortcut", "icon"] => Some(0),
+                    rels if rels.contains(&"icon") => Some(1),
+                    ["apple-touch-icon"] | ["apple-touch-icon-precomposed"] => Some(2),
+                    _ => None,
+                };
+                if let (Some(rank), Some(href)) = (rank, attribute("href")) {
+                    icons.push((rank, href.to_string()));
+                }
+            }
+            "base" => {
+                if let Some(url) = attribute("href").and_then(|href| page_url.join(href).ok()) {
+                    base_url = url;
+                }
+            }
+            "script" | "style" => {
+                let closing = format!("</{}", name);
+                let end = find_ignore_case(rest.as_bytes(), closing.as_bytes()).unwrap_or(rest.len());
+                rest = &rest[end..];
+            }
+            "body" => break,
+            _ => {}
+        }
+    }
+
+    let property = |keys: &[&str]| keys.iter().find_map(|key| properties.get(*key).map(String::as_str));
+    icons.sort_by_key(|(rank, _)| *rank);
+
+    PageMetadata {
+        title: property(&["og:title", "twitter:title"])
+            .or(title.as_deref())
+            .and_then(|title| clean_text(title, MAX_TITLE_LENGTH)),
+        description: property(&["og:description", "twitter:description", "description"])
+            .and_then(|description| clean_text(description, MAX_DESCRIPTION_LENGTH)),
+        image: property(&["og:image", "og:image:url", "og:image:secure_url", "twitter:image"])
+            .and_then(|image| resolve_asset(&base_url, image)),
+        site_name: property(&["og:site_name"]).and_then(|name| clean_text(name, MAX_TITLE_LENGTH)),
+        favicon: icons
+            .iter()
+            .find_map(|(_, href)| resolve_asset(&base_url, href))
+            .or_else(|| resolve_asset(page_url, "/favicon.ico")),
+        final_url: Some(page_url.to_string()),
+        fetched_at: Utc::now(),
+        error: None,
+    }
+}
+
+/// Attributes of a tag up to its closing `>`, names lowercased and values
+/// entity-decoded, and the input after the tag
+fn parse_attributes(mut input: &str) -> (Vec<(String, String)>, &str) {
+    let mut attributes = Vec::new();
+    loop {
+        input = input.trim_start();
+        match input.chars().next() {
+            None => return (attributes, input),
+            Some('>') => return (attributes, &input[1..]),
+            Some('/') => {
+                input = &input[1..];
+                continue;
+            }
+            _ => {}
+        }
+
+        let name_end = input
+            .find(|c: char| c.is_ascii_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(input.len());
+        if name_end == 0 {
+            // Stray '=' or similar; skip it
+            input = &input[1..];
+            continue;
+        }
+        let name = input[..name_end].to_ascii_lowercase();
+        input = input[name_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after_equals) = input.strip_prefix('=') {
+            input = after_equals.trim_start();
+            let (raw, after) = match input.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let body = &input[1..];
+                    match body.find(quote) {
+                        Some(end) => (&body[..end], &body[end + 1..]),
+                        None => (body, ""),
+                    }
+                }
+                _ => {
+                    let end = input
+                        .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                        .unwrap_or(input.len());
+                    (&input[..end], &input[end..])
+                }
+            };
+            value = decode_entities(raw);
+            input = after;
+        }
+        attributes.push((name, value));
+    }
+}
+
+/// Byte offset of the first case-insensitive occurrence of an ASCII `needle`
+fn find_ignore_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// Replace character references and the common named entities
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => None,
+    }
+}
+
+/// Collapse whitespace and cut to `max` characters, treating blank text as absent
+fn clean_text(text: &str, max: usize) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    Some(text.chars().take(max).collect())
+}
+
+/// Absolute HTTP(S) URL of an image or icon reference
+fn resolve_asset(base_url: &Url, reference: &str) -> Option<String> {
+    let url = base_url.join(reference.trim()).ok()?;
+    (matches!(url.scheme(), "http" | "https") && url.as_str().len() <= MAX_ASSET_URL_LENGTH)
+        .then(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use bytes::Bytes;
+    use futures_util::StreamExt;
+    use std::time::Instant;
+
+    const PAGE: &str = r#"<!doctype html>
+<html><head>
+<meta charset="utf-8">
+<title>Plain &amp; simple</title>
+<meta property="og:title" content="Launch &quot;day&quot;">
+<meta name="description" content="  Everything   you need  ">
+<meta property="og:image" content="/img/cover.png">
+<meta property="og:site_name" content="Example">
+<link rel="apple-touch-icon" href="/touch.png">
+<link rel="icon" href="/favicon.svg">
+</head><body><title>Not this one</title></body></html>"#;
+
+    /// Local stand-in for destination sites, on a random loopback port
+    async fn start_server() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = HttpServer::new(|| {
+            App::new()
+                .route(
+                    "/page",
+                    web::get()
+                        .to(|| async { HttpResponse::Ok().content_type("text/html").body(PAGE) }),
+                )
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                        HttpResponse::Ok().content_type("text/html").body(PAGE)
+                    }),
+                )
+                // Never ends, so only the byte limit stops the read
+                .route(
+                    "/endless",
+                    web::get().to(|| async {
+                        let chunk = Bytes::from(vec![b' '; 1024]);
+                        let head = Bytes::from_static(b"<html><head><title>Endless</title>");
+                        let stream =
+                            futures_util::stream::once(
+                                async move { Ok::<_, actix_web::Error>(head) },
+                            )
+                            .chain(futures_util::stream::repeat_with(move || Ok(chunk.clone())));
+                        HttpResponse::Ok()
+                            .content_type("text/html")
+                            .streaming(stream)
+                    }),
+                )
+                .route(
+                    "/late-title",
+                    web::get().to(|| async {
+                        let padding = " ".repeat(4096);
+                        HttpResponse::Ok()
+                            .content_type("text/html")
+                            .body(format!("<html><head>{}<title>Late</title></head>", padding))
+                    }),
+                )
+                .route(
+                    "/hop/{n}",
+                    web::get().to(|path: web::Path<u32>| async move {
+                        let n = path.into_inner();
+                        let location = if n == 0 {
+                            "/page".to_string()
+                        } else {
+                            format!("/hop/{}", n - 1)
+                        };
+                        HttpResponse::Found()
+                            .insert_header(("Location", location))
+                            .finish()
+                    }),
+                )
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        tokio::spawn(server);
+        format!("http://{}", address)
+    }
+
+    fn fetcher(configure: impl FnOnce(&mut PageFetchConfig)) -> HttpPageFetcher {
+        let mut config = PageFetchConfig {
+            allow_private_networks: true,
+            ..PageFetchConfig::default()
+        };
+        configure(&mut config);
+        HttpPageFetcher::new(&config).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn fetches_page_metadata() {
+        let base = start_server().await;
+        let metadata = fetcher(|_| {}).fetch(&format!("{}/page", base)).await;
+
+        assert_eq!(metadata.error, None);
+        assert_eq!(metadata.title.as_deref(), Some("Launch \"day\""));
+        assert_eq!(metadata.description.as_deref(), Some("Everything you need"));
+        assert_eq!(metadata.image, Some(format!("{}/img/cover.png", base)));
+        assert_eq!(metadata.favicon, Some(format!("{}/favicon.svg", base)));
+        assert_eq!(metadata.final_url, Some(format!("{}/page", base)));
+    }
+
+    #[actix_web::test]
+    async fn gives_up_after_the_timeout() {
+        let base = start_server().await;
+        let started = Instant::now();
+        let metadata = fetcher(|config| config.timeout_ms = 200)
+            .fetch(&format!("{}/slow", base))
+            .await;
+
+        assert!(metadata.error.unwrap().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[actix_web::test]
+    async fn stops_reading_at_max_bytes() {
+        let base = start_server().await;
+        let fetcher = fetcher(|config| {
+            config.max_bytes = 2048;
+            config.timeout_ms = 2000;
+        });
+
+        let endless = fetcher.fetch(&format!("{}/endless", base)).await;
+        assert_eq!(endless.error, None);
+        assert_eq!(endless.title.as_deref(), Some("Endless"));
+
+        let late = fetcher.fetch(&format!("{}/late-title", base)).await;
+        assert_eq!(late.error, None);
+        assert_eq!(late.title, None);
+    }
+
+    #[actix_web::test]
+    async fn follows_at_most_max_redirects() {
+        let base = start_server().await;
+        let fetcher = fetcher(|config| config.max_redirects = 3);
+
+        let followed = fetcher.fetch(&format!("{}/hop/2", base)).await;
+        assert_eq!(followed.error, None);
+        assert_eq!(followed.final_url, Some(format!("{}/page", base)));
+
+        let too_many = fetcher.fetch(&format!("{}/hop/3", base)).await;
+        assert_eq!(too_many.error.as_deref(), Some("more than 3 redirects"));
+    }
+
+    #[actix_web::test]
+    async fn refuses_private_destinations() {
+        let base = start_server().await;
+        let port = base.rsplit(':').next().unwrap();
+        let fetcher = HttpPageFetcher::new(&PageFetchConfig::default()).unwrap();
+
+        let literal = fetcher.fetch(&format!("{}/page", base)).await;
+        assert_eq!(
+            literal.error.as_deref(),
+            Some("127.0.0.1 is not a public address")
+        );
+
+        // The name is public, the address it resolves to is not
+        let resolved = fetcher
+            .fetch(&format!("http://localhost:{}/page", port))
+            .await;
+        assert!(resolved
+            .error
+            .unwrap()
+            .contains("localhost has no public address"));
+
+        for url in [
+            "http://[::1]/",
+            "http://10.1.2.3/",
+            "http://169.254.169.254/",
+            "ftp://example.com/",
+        ] {
+            assert!(
+                fetcher
+                    .check_destination(&Url::parse(url).unwrap())
+                    .is_err(),
+                "{}",
+                url
+            );
+        }
+        assert!(fetcher
+            .check_destination(&Url::parse("http://93.184.215.14/").unwrap())
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn resolver_drops_private_addresses() {
+        let resolve = |allow_private_networks| {
+            PublicResolver {
+                allow_private_networks,
+            }
+            .resolve("localhost".parse().unwrap())
+        };
+
+        assert!(resolve(false).await.is_err());
+        assert!(resolve(true)
+            .await
+            .unwrap()
+            .all(|addr| addr.ip().is_loopback()));
+    }
+
+    #[test]
+    fn classifies_public_addresses() {
+        for ip in ["8.8.8.8", "2606:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "192.168.1.1",
+            "100.64.0.1",
+            "0.1.2.3",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn extracts_title_og_tags_and_favicon() {
+        let page_url = Url::parse("https://example.com/blog/post").unwrap();
+        let metadata = extract_page_metadata(PAGE, &page_url);
+
+        assert_eq!(metadata.title.as_deref(), Some("Launch \"day\""));
+        assert_eq!(metadata.description.as_deref(), Some("Everything you need"));
+        assert_eq!(
+            metadata.image.as_deref(),
+            Some("https://example.com/img/cover.png")
+        );
+        assert_eq!(metadata.site_name.as_deref(), Some("Example"));
+        assert_eq!(
+            metadata.favicon.as_deref(),
+            Some("https://example.com/favicon.svg")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_title_tag_and_default_favicon() {
+        let page_url = Url::parse("https://example.com/a/b").unwrap();
+        let html = r#"<HEAD><!-- <title>Commented</title> --><script>"<title>Script</title>"</script>
+            <TITLE> Tom &#38; Jerry&#x21; </TITLE><base href="https://cdn.example.net/assets/">
+            <meta property="og:image" content="cover.jpg"></HEAD>"#;
+        let metadata = extract_page_metadata(html, &page_url);
+
+        assert_eq!(metadata.title.as_deref(), Some("Tom & Jerry!"));
+        assert_eq!(metadata.description, None);
+        assert_eq!(
+            metadata.image.as_deref(),
+            Some("https://cdn.example.net/assets/cover.jpg")
+        );
+        assert_eq!(
+            metadata.favicon.as_deref(),
+            Some("https://example.com/favicon.ico")
+        );
+    }
+}
@@ -1,12 +1,26 @@
-use crate::models::Result;
+use crate::models::{AppError, Result};
 use crate::traits::ShortCodeGenerator;
-use base64::{engine::general_purpose, Engine as _};
+use once_cell::sync::Lazy;
+use rand::seq::SliceRandom;
 use rand::Rng;
 use sha2::{Digest, Sha256};
 
 /// Base62 alphabet for short codes
 const BASE62_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
 
+/// Maximum length of a custom short code
+const MAX_CUSTOM_CODE_LENGTH: usize = 20;
+
+/// Maximum number of free alternatives returned for a taken custom code
+pub const MAX_CODE_SUGGESTIONS: usize = 5;
+
+/// Embedded wordlists for human-readable codes, at most 5 letters per word
+static ADJECTIVES: Lazy<Vec<&'static str>> =
+    Lazy::new(|| include_str!("wordlists/adjectives.txt").split_whitespace().collect());
+static NOUNS: Lazy<Vec<&'static str>> =
+    Lazy::new(|| include_str!("wordlists/nouns.txt").split_whitespace().collect());
+const MAX_WORD_LENGTH: usize = 5;
+
 /// High-performance short code generator with multiple strategies
 pub struct DefaultShortCodeGenerator;
 
@@ -36,15 +50,26 @@ impl ShortCodeGenerator for DefaultShortCodeGenerator {
             ));
         }
         
-        // Only allow alphanumeric characters and hyphens
-        if !custom_code.chars().all(|c| c.is_alphanumeric() || c == '-') {
+        // Only allow alphanumeric characters, hyphens and underscores, as in generated word codes
+        if !custom_code.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
             return Err(crate::models::AppError::Validation(
-                "Custom code can only contain alphanumeric characters and hyphens".to_string()
+                "Custom code can only contain alphanumeric characters, hyphens and underscores".to_string()
             ));
         }
+
+        if crate::routes::is_reserved_code(custom_code) {
+            return Err(crate::models::AppError::Validation(format!(
+                "Custom code '{}' is reserved for the service's own routes",
+                custom_code
+            )));
+        }
         
         Ok(custom_code.to_string())
     }
+
+    fn suggest_alternatives(&self, custom_code: &str) -> Vec<String> {
+        code_variants(custom_code)
+    }
 }
 
 impl DefaultShortCodeGenerator {
@@ -96,6 +121,226 @@ impl ShortCodeGenerator for RandomShortCodeGenerator {
     fn generate_custom(&self, custom_code: &str) -> Result<String> {
         DefaultShortCodeGenerator::new().generate_custom(custom_code)
     }
+
+    fn suggest_alternatives(&self, custom_code: &str) -> Vec<String> {
+        code_variants(custom_code)
+    }
+}
+
+/// Human-readable generator producing codes like `brave-otter-42`
+///
+/// Codes are adjectives followed by a noun and an optional numeric suffix.
+/// The requested length is ignored; code size is set by the word and digit
+/// counts, which must fit within the short code column.
+pub struct WordShortCodeGenerator {
+    word_count: usize,
+    separator: String,
+    digits: u32,
+}
+
+impl WordShortCodeGenerator {
+    pub fn new(word_count: usize, separator: &str, digits: u32) -> Result<Self> {
+        if word_count == 0 {
+            return Err(AppError::Validation(
+                "Word-based codes need at least one word".to_string(),
+            ));
+        }
+
+        if !matches!(separator, "" | "-" | "_") {
+            return Err(AppError::Validation(
+                "Word separator must be '-', '_' or empty".to_string(),
+            ));
+        }
+
+        // Worst case: every word at maximum length plus separators and suffix
+        let suffix_length = if digits > 0 { separator.len() + digits as usize } else { 0 };
+        let max_length =
+            word_count * MAX_WORD_LENGTH + (word_count - 1) * separator.len() + suffix_length;
+        if max_length > MAX_CUSTOM_CODE_LENGTH {
+            return Err(AppError::Validation(format!(
+                "Word-based codes can reach {} characters, limit is {}",
+                max_length, MAX_CUSTOM_CODE_LENGTH
+            )));
+        }
+
+        Ok(Self {
+            word_count,
+            separator: separator.to_string(),
+            digits,
+        })
+    }
+}
+
+impl ShortCodeGenerator for WordShortCodeGenerator {
+    fn generate(&self, _url: &str, _length: usize) -> String {
+        let mut rng = rand::thread_rng();
+        let mut parts: Vec<String> = (1..self.word_count)
+            .filter_map(|_| ADJECTIVES.choose(&mut rng))
+            .map(|word| word.to_string())
+            .collect();
+        if let Some(noun) = NOUNS.choose(&mut rng) {
+            parts.push(noun.to_string());
+        }
+
+        if self.digits > 0 {
+            let number = rng.gen_range(0..10u64.pow(self.digits));
+            parts.push(format!("{:0width$}", number, width = self.digits as usize));
+        }
+
+        parts.join(&self.separator)
+    }
+
+    fn generate_custom(&self, custom_code: &str) -> Result<String> {
+        DefaultShortCodeGenerator::new().generate_custom(custom_code)
+    }
+
+    fn entropy_bits(&self, _length: usize) -> f64 {
+        (self.word_count - 1) as f64 * (ADJECTIVES.len() as f64).log2()
+            + (NOUNS.len() as f64).log2()
+            + self.digits as f64 * 10f64.log2()
+    }
+
+    fn suggest_alternatives(&self, custom_code: &str) -> Vec<String> {
+        code_variants(custom_code)
+    }
+}
+
+/// Short code scheme selected at startup
+pub enum ShortCodeStrategy {
+    Base62(DefaultShortCodeGenerator),
+    Random(RandomShortCodeGenerator),
+    Words(WordShortCodeGenerator),
+}
+
+impl ShortCodeStrategy {
+    /// Build the generator named by `style` (`base62`, `random` or `words`)
+    pub fn from_style(style: &str, word_count: usize, separator: &str, digits: u32) -> Result<Self> {
+        match style {
+            "base62" => Ok(Self::Base62(DefaultShortCodeGenerator::new())),
+            "random" => Ok(Self::Random(RandomShortCodeGenerator)),
+            "words" => Ok(Self::Words(WordShortCodeGenerator::new(word_count, separator, digits)?)),
+            other => Err(AppError::Validation(format!("Unknown short code style '{}'", other))),
+        }
+    }
+
+    fn inner(&self) -> &(dyn ShortCodeGenerator + Send + Sync) {
+        match self {
+            Self::Base62(generator) => generator,
+            Self::Random(generator) => generator,
+            Self::Words(generator) => generator,
+        }
+    }
+}
+
+impl ShortCodeGenerator for ShortCodeStrategy {
+    fn generate(&self, url: &str, length: usize) -> String {
+        self.inner().generate(url, length)
+    }
+
+    fn generate_custom(&self, custom_code: &str) -> Result<String> {
+        self.inner().generate_custom(custom_code)
+    }
+
+    fn entropy_bits(&self, length: usize) -> f64 {
+        self.inner().entropy_bits(length)
+    }
+
+    fn suggest_alternatives(&self, custom_code: &str) -> Vec<String> {
+        self.inner().suggest_alternatives(custom_code)
+    }
+}
+
+/// Build candidate alternatives for a custom code: hyphenated and joined
+/// variants, reordered words, then numeric and random suffixes.
+/// Candidates are valid custom codes but are not checked for availability.
+pub fn code_variants(custom_code: &str) -> Vec<String> {
+    let words = split_words(custom_code);
+    let mut candidates = Vec::new();
+
+    if words.len() > 1 {
+        // Hyphenated and joined forms of the same words
+        candidates.push(words.join("-"));
+        candidates.push(words.concat());
+
+        // Reordered words: reversed, then each rotation
+        let mut reversed = words.clone();
+        reversed.reverse();
+        candidates.push(reversed.join("-"));
+        for shift in 1..words.len() {
+            let mut rotated = words.clone();
+            rotated.rotate_left(shift);
+            candidates.push(rotated.join("-"));
+        }
+    }
+
+    // Numeric suffixes
+    for n in 1..=9 {
+        candidates.push(with_suffix(custom_code, &format!("-{}", n)));
+        candidates.push(with_suffix(custom_code, &n.to_string()));
+    }
+
+    // Random suffixes as a last resort when the numbered forms are taken too
+    let mut rng = rand::thread_rng();
+    for _ in 0..MAX_CODE_SUGGESTIONS {
+        let suffix: String = (0..3)
+            .map(|_| BASE62_ALPHABET[rng.gen_range(10..36)].to_ascii_lowercase() as char)
+            .collect();
+        candidates.push(with_suffix(custom_code, &format!("-{}", suffix)));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|c| c != custom_code && is_valid_custom_code(c) && seen.insert(c.clone()))
+        .collect()
+}
+
+/// Split a code into words on hyphens, underscores, letter/digit and lower/upper case boundaries
+fn split_words(code: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev: Option<char> = None;
+
+    for c in code.chars() {
+        if c == '-' || c == '_' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev = None;
+            continue;
+        }
+
+        if let Some(p) = prev {
+            let boundary = (p.is_alphabetic() && c.is_numeric())
+                || (p.is_numeric() && c.is_alphabetic())
+                || (p.is_lowercase() && c.is_uppercase());
+            if boundary && !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+
+        current.push(c);
+        prev = Some(c);
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+/// Append a suffix, truncating the base so the result still fits the length limit
+fn with_suffix(base: &str, suffix: &str) -> String {
+    let room = MAX_CUSTOM_CODE_LENGTH.saturating_sub(suffix.chars().count());
+    let base: String = base.chars().take(room).collect();
+    format!("{}{}", base.trim_end_matches(['-', '_']), suffix)
+}
+
+fn is_valid_custom_code(code: &str) -> bool {
+    !code.is_empty()
+        && code.len() <= MAX_CUSTOM_CODE_LENGTH
+        && code.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
 }
 
 impl Default for DefaultShortCodeGenerator {
@@ -1,28 +1,73 @@
+use crate::core::background::BackgroundTasks;
+use crate::core::metrics;
 use crate::models::{
-    AppError, CreateUrlRequest, CreateUrlResponse, Result, TinyUrl, UrlStatsResponse,
+    normalize_host, normalize_tags, AppError, CacheRebuildSummary, CodeAvailabilityResponse, CreateDomainRequest,
+    CreateUrlRequest, CreateUrlResponse, Destination, Domain, ImportOptions, ImportReport, link_key, LinkFilter,
+    LinkListQuery, LinkListResponse, LinkMetadata, LinkSummary, normalize_variants, Redirect, Result, TinyUrl,
+    ClickEvent, RollupReport, TimeseriesInterval, TimeseriesPoint, TimeseriesResponse, TransferFormat,
+    UpdateUrlRequest, UrlStatsResponse, validate_schedule, VariantStats, VisitContext, DEFAULT_DOMAIN,
+    MAX_IMPORT_ISSUES, MAX_TIMESERIES_POINTS,
+};
+use crate::services::{parse_links, MAX_CODE_SUGGESTIONS};
+use crate::traits::{
+    CacheService, DomainRepository, LinkTransferRepository, PageFetcher, ShortCodeGenerator,
+    UrlRepository, UrlService,
 };
-use crate::traits::{CacheService, ShortCodeGenerator, UrlRepository, UrlService};
 use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::stream::BoxStream;
+use sqlx::types::Json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
 use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{instrument, Instrument};
+
+/// Links read per query when scanning the whole table
+const SCAN_PAGE_SIZE: i64 = 1000;
+
+/// Raw clicks rolled up per transaction, so a backlog never holds one huge
+/// transaction and its row locks
+const ROLLUP_BATCH_SIZE: i64 = 10_000;
+
+/// Link listing page size when none is requested
+const DEFAULT_LIST_LIMIT: i64 = 50;
+
+/// Largest link listing page
+const MAX_LIST_LIMIT: i64 = 200;
+
+/// Days of daily unique visitor counts in link stats
+const DAILY_UNIQUES_DAYS: u64 = 7;
+
+/// How long a day's unique visitor count is kept, in seconds; a day longer
+/// than it is shown, as the oldest day shown started up to a day earlier
+const DAILY_UNIQUES_TTL: u64 = (DAILY_UNIQUES_DAYS + 1) * 24 * 60 * 60;
 
 /// High-performance URL service implementation
 pub struct DefaultUrlService<R, C, G>
 where
-    R: UrlRepository + Send + Sync + 'static,
+    R: UrlRepository + DomainRepository + LinkTransferRepository + Send + Sync + 'static,
     C: CacheService + Send + Sync + 'static,
     G: ShortCodeGenerator + Send + Sync + 'static,
 {
     repository: Arc<R>,
     cache: Arc<C>,
     generator: Arc<G>,
-    base_url: String,
     default_short_code_length: usize,
     cache_ttl: u64,
+    /// How long a deleted link's code stays reserved
+    code_quarantine: chrono::Duration,
+    background: BackgroundTasks,
+    /// Reads destination page metadata; none are fetched without one
+    page_fetcher: Option<Arc<dyn PageFetcher + Send + Sync>>,
+    /// How long hourly click counts are kept; hourly time series cannot start earlier
+    hourly_click_retention: Option<chrono::Duration>,
 }
 
 impl<R, C, G> DefaultUrlService<R, C, G>
 where
-    R: UrlRepository + Send + Sync + 'static,
+    R: UrlRepository + DomainRepository + LinkTransferRepository + Send + Sync + 'static,
     C: CacheService + Send + Sync + 'static,
     G: ShortCodeGenerator + Send + Sync + 'static,
 {
@@ -30,31 +75,165 @@ where
         repository: Arc<R>,
         cache: Arc<C>,
         generator: Arc<G>,
-        base_url: String,
         default_short_code_length: usize,
         cache_ttl: u64,
+        code_quarantine: chrono::Duration,
+        background: BackgroundTasks,
     ) -> Self {
         Self {
             repository,
             cache,
             generator,
-            base_url,
             default_short_code_length,
             cache_ttl,
+            code_quarantine,
+            background,
+            page_fetcher: None,
+            hourly_click_retention: None,
+        }
+    }
+
+    /// Fetch destination page metadata in the background when links are
+    /// created or their destination changes
+    pub fn with_page_fetcher(mut self, page_fetcher: Arc<dyn PageFetcher + Send + Sync>) -> Self {
+        self.page_fetcher = Some(page_fetcher);
+        self
+    }
+
+    /// Refuse hourly time series reaching back past the hourly counts' retention
+    pub fn with_hourly_click_retention(mut self, retention: chrono::Duration) -> Self {
+        self.hourly_click_retention = Some(retention);
+        self
+    }
+
+    /// Fetch and store a link's page metadata in the background
+    fn fetch_page_metadata(&self, url: &TinyUrl) {
+        let Some(page_fetcher) = self.page_fetcher.clone() else {
+            return;
+        };
+        // A template is no page of its own
+        if url.template {
+            return;
         }
+
+        let repo = Arc::clone(&self.repository);
+        let cache = Arc::clone(&self.cache);
+        let (domain, short_code, long_url) =
+            (url.domain.clone(), url.short_code.clone(), url.long_url.clone());
+        let interstitial = url.interstitial;
+        self.background.spawn(
+            "page_fetch",
+            async move {
+                let metadata = page_fetcher.fetch(&long_url).await;
+                // Dropped if the destination changed meanwhile; that change fetches again
+                let result = match repo
+                    .set_page_metadata(&domain, &short_code, &long_url, &metadata)
+                    .await
+                {
+                    // Cached destinations of interstitial links carry the preview
+                    Ok(true) if interstitial => cache.delete(&url_cache_key(&domain, &short_code)).await,
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    tracing::error!("Failed to store page metadata: {}", e);
+                    metrics::BACKGROUND_TASK_ERRORS.with_label_values(&["page_fetch"]).inc();
+                }
+            }
+            .instrument(tracing::info_span!("page_fetch", short_code = %url.short_code)),
+        );
+    }
+
+    /// Count a click by the visitor's country and the variant picked, in the
+    /// background, when known; a bot's visit only counts as a bot click
+    fn record_visit(&self, domain: &str, short_code: &str, visit: &VisitContext, redirect: &Redirect) {
+        if let Some(bot) = visit.bot.clone() {
+            let repo = Arc::clone(&self.repository);
+            let (domain, code) = (domain.to_string(), short_code.to_string());
+            self.background.spawn(
+                "bot_click",
+                async move {
+                    if let Err(e) = repo.record_bot_click(&domain, &code, &bot).await {
+                        tracing::error!("Failed to record bot click: {}", e);
+                        metrics::BACKGROUND_TASK_ERRORS.with_label_values(&["bot_click"]).inc();
+                    }
+                }
+                .instrument(tracing::info_span!("bot_click", short_code)),
+            );
+            return;
+        }
+
+        let event = ClickEvent {
+            referrer: visit.referrer.clone(),
+            country: visit.country.clone(),
+            device: visit.device.device,
+            browser: visit.device.browser,
+        };
+        let variant = redirect.variant.clone();
+        let visitor_key = visit.visitor_key.clone();
+        let (repo, cache) = (Arc::clone(&self.repository), Arc::clone(&self.cache));
+        let (all_time, daily) = (
+            uniques_cache_key(domain, short_code),
+            daily_uniques_cache_key(domain, short_code, Utc::now().date_naive()),
+        );
+        let (domain, code) = (domain.to_string(), short_code.to_string());
+        // One task per visit: a single statement for the click and its tallies,
+        // and a single Redis round trip for both unique counts
+        self.background.spawn(
+            "click_event",
+            async move {
+                let (click, uniques) = tokio::join!(
+                    repo.record_click_event(&domain, &code, &event, variant.as_deref()),
+                    async {
+                        match &visitor_key {
+                            Some(visitor_key) => {
+                                let counts = [(all_time.as_str(), None), (daily.as_str(), Some(DAILY_UNIQUES_TTL))];
+                                cache.add_uniques(&counts, visitor_key).await
+                            }
+                            None => Ok(()),
+                        }
+                    }
+                );
+                if let Err(e) = click {
+                    tracing::error!("Failed to record click event: {}", e);
+                    metrics::BACKGROUND_TASK_ERRORS.with_label_values(&["click_event"]).inc();
+                }
+                if let Err(e) = uniques {
+                    tracing::error!("Failed to count unique visitor: {}", e);
+                    metrics::BACKGROUND_TASK_ERRORS.with_label_values(&["unique_visit"]).inc();
+                }
+            }
+            .instrument(tracing::info_span!("click_event", short_code)),
+        );
     }
 
     /// Generate unique short code
-    async fn generate_unique_short_code(&self, url: &str, custom_code: Option<&str>) -> Result<String> {
+    #[instrument(skip(self, url))]
+    async fn generate_unique_short_code(
+        &self,
+        domain: &str,
+        url: &str,
+        custom_code: Option<&str>,
+    ) -> Result<String> {
         if let Some(custom) = custom_code {
             let code = self.generator.generate_custom(custom)?;
             
-            // Check if custom code already exists
-            if self.repository.exists(&code).await? {
-                return Err(AppError::AlreadyExists(format!(
-                    "Custom code '{}' already exists",
-                    custom
-                )));
+            // Check if custom code already exists; a code deleted long enough ago is released
+            if self.repository.exists(domain, &code).await? {
+                if !self
+                    .repository
+                    .release_code(domain, &code, Utc::now() - self.code_quarantine)
+                    .await?
+                {
+                    return Err(AppError::CodeTaken {
+                        suggestions: self.suggest_free_codes(domain, &code).await?,
+                        code,
+                    });
+                }
+                // The old link is gone for good, so its counts must not carry over
+                for key in counter_cache_keys(domain, &code) {
+                    self.cache.delete(&key).await?;
+                }
             }
             
             return Ok(code);
@@ -67,10 +246,11 @@ where
         while attempts < MAX_ATTEMPTS {
             let code = self.generator.generate(url, self.default_short_code_length);
             
-            if !self.repository.exists(&code).await? {
+            if !self.repository.exists(domain, &code).await? {
                 return Ok(code);
             }
             
+            metrics::SHORT_CODE_COLLISIONS.inc();
             attempts += 1;
         }
 
@@ -79,27 +259,182 @@ where
         ))
     }
 
-    /// Build full short URL
-    fn build_short_url(&self, short_code: &str) -> String {
-        format!("{}/{}", self.base_url.trim_end_matches('/'), short_code)
+    /// Collect alternatives to a taken custom code that are verified free
+    #[instrument(skip(self))]
+    async fn suggest_free_codes(&self, domain: &str, code: &str) -> Result<Vec<String>> {
+        let candidates = self.generator.suggest_alternatives(code);
+        let taken: HashSet<String> = self
+            .repository
+            .existing_codes(domain, &candidates)
+            .await?
+            .into_iter()
+            .collect();
+
+        Ok(candidates
+            .into_iter()
+            .filter(|candidate| !taken.contains(candidate))
+            .take(MAX_CODE_SUGGESTIONS)
+            .collect())
+    }
+
+    /// Whether a deleted link's quarantine is over, so its code may be claimed again
+    fn is_released(&self, url: &TinyUrl, now: DateTime<Utc>) -> bool {
+        url.deleted_at
+            .is_some_and(|deleted_at| deleted_at + self.code_quarantine <= now)
+    }
+
+    /// Resolve an optional domain host to a registered domain key
+    async fn registered_domain_key(&self, domain: Option<&str>) -> Result<String> {
+        match domain.map(normalize_host).filter(|host| !host.is_empty()) {
+            Some(host) => self
+                .resolve_domain(&host)
+                .await?
+                .map(|d| d.host)
+                .ok_or_else(|| AppError::Validation(format!("Unknown domain '{}'", host))),
+            None => Ok(DEFAULT_DOMAIN.to_string()),
+        }
     }
+
+    /// Cache lifetime of a link's destination, never outliving its expiry or
+    /// lasting past the start or end of its activation window
+    fn cache_ttl_for(&self, url: &TinyUrl, now: DateTime<Utc>) -> u64 {
+        [url.expires_at, url.active_from, url.active_until]
+            .into_iter()
+            .flatten()
+            .filter(|at| *at > now)
+            .map(|at| (at - now).num_seconds().max(1) as u64)
+            .fold(self.cache_ttl, u64::min)
+    }
+
+    /// Existing link, or a `NotFound` error
+    async fn require_url(&self, domain: &str, short_code: &str) -> Result<TinyUrl> {
+        self.repository
+            .find_by_short_code(domain, short_code)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Short code '{}' not found", short_code)))
+    }
+
+    /// Drop a link's cached destination so the next redirect reads the database
+    async fn invalidate(&self, domain: &str, short_code: &str) -> Result<()> {
+        self.cache.delete(&url_cache_key(domain, short_code)).await
+    }
+
+    /// Build full short URL, preferring the custom domain's base URL
+    async fn build_short_url(&self, domain: &str, short_code: &str, base_url: &str) -> Result<String> {
+        let base_url = match self.resolve_domain(domain).await? {
+            Some(d) => d.base_url,
+            None => base_url.to_string(),
+        };
+
+        Ok(format!("{}/{}", base_url.trim_end_matches('/'), short_code))
+    }
+}
+
+/// Cache key of a short code; default-domain keys are the bare code
+fn url_cache_key(domain: &str, short_code: &str) -> String {
+    if domain == DEFAULT_DOMAIN {
+        short_code.to_string()
+    } else {
+        format!("{}/{}", domain, short_code)
+    }
+}
+
+/// Cache key of a short code's click counter
+fn clicks_cache_key(domain: &str, short_code: &str) -> String {
+    format!("clicks:{}", url_cache_key(domain, short_code))
+}
+
+/// Cache key of a short code's all-time unique visitor count
+fn uniques_cache_key(domain: &str, short_code: &str) -> String {
+    format!("uniques:{}", url_cache_key(domain, short_code))
+}
+
+/// Cache key of a short code's unique visitor count on one UTC day
+fn daily_uniques_cache_key(domain: &str, short_code: &str, day: NaiveDate) -> String {
+    format!("{}:{}", uniques_cache_key(domain, short_code), day.format("%Y-%m-%d"))
+}
+
+/// Cache keys of a short code's click and unique visitor counters, which
+/// outlive soft deletion and are dropped when the code is released
+fn counter_cache_keys(domain: &str, short_code: &str) -> Vec<String> {
+    let today = Utc::now().date_naive();
+    [clicks_cache_key(domain, short_code), uniques_cache_key(domain, short_code)]
+        .into_iter()
+        .chain(
+            (0..=DAILY_UNIQUES_DAYS).map(|days| {
+                daily_uniques_cache_key(domain, short_code, today - chrono::Days::new(days))
+            }),
+        )
+        .collect()
+}
+
+/// Where a visit at `now` goes: the link's destination while its activation
+/// window is open, its fallback URL outside it. A visit the link has no
+/// destination for, e.g. a path it does not forward, finds no link.
+fn visit_redirect(
+    destination: Destination,
+    domain: &str,
+    short_code: &str,
+    visit: &VisitContext,
+    now: DateTime<Utc>,
+) -> Result<Redirect> {
+    if !destination.is_live(now) {
+        return destination.fallback().ok_or_else(|| {
+            AppError::NotFound(format!("Short code '{}' is not active at this time", short_code))
+        });
+    }
+    destination.resolve(visit, &link_key(domain, short_code)).ok_or_else(|| {
+        AppError::NotFound(format!(
+            "Short code '{}' has no destination for this path or query",
+            short_code
+        ))
+    })
 }
 
 #[async_trait]
 impl<R, C, G> UrlService for DefaultUrlService<R, C, G>
 where
-    R: UrlRepository + Send + Sync + 'static,
+    R: UrlRepository + DomainRepository + LinkTransferRepository + Send + Sync + 'static,
     C: CacheService + Send + Sync + 'static,
     G: ShortCodeGenerator + Send + Sync + 'static,
 {
-    async fn create_short_url(&self, request: CreateUrlRequest) -> Result<CreateUrlResponse> {
+    #[instrument(skip_all, fields(domain = ?request.domain, custom_code = ?request.custom_code))]
+    async fn create_short_url(
+        &self,
+        request: CreateUrlRequest,
+        base_url: &str,
+    ) -> Result<CreateUrlResponse> {
         // Validate request
         request.validate()?;
+        let metadata = request.metadata()?;
+        let variants = request.variants()?;
+        let utm = request.utm()?;
+
+        let domain = self.registered_domain_key(request.domain.as_deref()).await?;
 
-        // Check if URL already exists
-        if let Some(existing) = self.repository.find_by_long_url(&request.url).await? {
+        // Reuse an active link to the same URL with the same expiry and metadata
+        let existing = self
+            .repository
+            .find_by_long_url(&domain, &request.url)
+            .await?
+            .filter(|existing| {
+                existing.is_active(Utc::now())
+                    && existing.expires_at == request.expires_at
+                    && existing.interstitial == request.interstitial
+                    && existing.redirect_rules.0 == request.redirect_rules
+                    && existing.variants.0 == variants
+                    && existing.locked_variant == request.locked_variant
+                    && existing.utm.0 == utm
+                    && existing.passthrough == request.passthrough
+                    && existing.template == request.template
+                    && existing.active_from == request.active_from
+                    && existing.active_until == request.active_until
+                    && existing.fallback_url == request.fallback_url
+                    && existing.metadata() == metadata
+            });
+        if let Some(existing) = existing {
             return Ok(CreateUrlResponse {
-                short_url: self.build_short_url(&existing.short_code),
+                short_url: self.build_short_url(&domain, &existing.short_code, base_url).await?,
                 long_url: existing.long_url,
                 short_code: existing.short_code,
                 qr_code: existing.qr_code,
@@ -108,93 +443,624 @@ where
 
         // Generate unique short code
         let short_code = self
-            .generate_unique_short_code(&request.url, request.custom_code.as_deref())
+            .generate_unique_short_code(&domain, &request.url, request.custom_code.as_deref())
             .await?;
 
         // Create URL entity
-        let url = TinyUrl::new(short_code.clone(), request.url.clone());
+        let mut url = TinyUrl::new(short_code.clone(), request.url.clone());
+        url.domain = domain.clone();
+        url.expires_at = request.expires_at;
+        url.interstitial = request.interstitial;
+        url.redirect_rules = Json(request.redirect_rules);
+        url.variants = Json(variants);
+        url.locked_variant = request.locked_variant;
+        url.utm = Json(utm);
+        url.passthrough = request.passthrough;
+        url.template = request.template;
+        url.active_from = request.active_from;
+        url.active_until = request.active_until;
+        url.fallback_url = request.fallback_url;
+        url.set_metadata(metadata);
 
         // Save to database
         let saved_url = self.repository.create(&url).await?;
+        self.fetch_page_metadata(&saved_url);
 
         // Cache the URL for fast lookups
         self.cache
-            .set(&short_code, &saved_url.long_url, self.cache_ttl)
+            .set(
+                &url_cache_key(&domain, &short_code),
+                &Destination::of(&saved_url).to_cache_value(),
+                self.cache_ttl_for(&saved_url, Utc::now()),
+            )
             .await?;
 
         Ok(CreateUrlResponse {
-            short_url: self.build_short_url(&saved_url.short_code),
+            short_url: self.build_short_url(&domain, &saved_url.short_code, base_url).await?,
             long_url: saved_url.long_url,
             short_code: saved_url.short_code,
             qr_code: saved_url.qr_code,
         })
     }
 
-    async fn get_original_url(&self, short_code: &str) -> Result<String> {
+    #[instrument(skip(self))]
+    async fn get_original_url(
+        &self,
+        domain: &str,
+        short_code: &str,
+        visit: &VisitContext,
+    ) -> Result<Redirect> {
+        let cache_key = url_cache_key(domain, short_code);
+        let now = Utc::now();
+
         // Try cache first for maximum performance
-        if let Some(cached_url) = self.cache.get(short_code).await? {
+        if let Some(cached) = self.cache.get(&cache_key).await? {
+            let destination = Destination::from_cache_value(&cached);
+            let redirect = visit_redirect(destination, domain, short_code, visit, now)
+                .inspect_err(|_| metrics::REDIRECTS.with_label_values(&["not_found"]).inc())?;
             // Increment clicks asynchronously
-            let _ = self.cache.increment_clicks(short_code).await;
-            return Ok(cached_url);
+            if visit.bot.is_none() {
+                let _ = self.cache.increment_clicks(&cache_key).await;
+            }
+            metrics::REDIRECTS.with_label_values(&["hit"]).inc();
+            self.record_visit(domain, short_code, visit, &redirect);
+            return Ok(redirect);
         }
 
-        // Fallback to database
-        let url = self
-            .repository
-            .find_by_short_code(short_code)
-            .await?
-            .ok_or_else(|| AppError::NotFound(format!("Short code '{}' not found", short_code)))?;
+        // Fallback to database; inactive links are never cached
+        let url = match self.repository.find_by_short_code(domain, short_code).await? {
+            Some(url) if url.is_active(now) => url,
+            Some(url) if url.is_retired() => {
+                metrics::REDIRECTS.with_label_values(&["gone"]).inc();
+                return Err(AppError::Gone(format!(
+                    "Short code '{}' was disabled or deleted",
+                    short_code
+                )));
+            }
+            _ => {
+                metrics::REDIRECTS.with_label_values(&["not_found"]).inc();
+                return Err(AppError::NotFound(format!("Short code '{}' not found", short_code)));
+            }
+        };
 
         // Update cache
+        let destination = Destination::of(&url);
         self.cache
-            .set(short_code, &url.long_url, self.cache_ttl)
+            .set(&cache_key, &destination.to_cache_value(), self.cache_ttl_for(&url, now))
             .await?;
+        let redirect = visit_redirect(destination, domain, short_code, visit, now)
+            .inspect_err(|_| metrics::REDIRECTS.with_label_values(&["not_found"]).inc())?;
+        metrics::REDIRECTS.with_label_values(&["miss"]).inc();
 
-        // Increment clicks in database (async)
-        let mut updated_url = url.clone();
-        updated_url.increment_clicks();
-        
-        // Update in background - don't block the response
-        let repo = Arc::clone(&self.repository);
-        let url_for_update = updated_url.clone();
-        tokio::spawn(async move {
-            if let Err(e) = repo.update(&url_for_update).await {
-                log::error!("Failed to update click count: {}", e);
-            }
-        });
+        // Increment clicks in background - don't block the response; drained on shutdown
+        if visit.bot.is_none() {
+            let repo = Arc::clone(&self.repository);
+            let (domain_key, code) = (url.domain.clone(), url.short_code.clone());
+            self.background.spawn(
+                "click_update",
+                async move {
+                    if let Err(e) = repo.increment_clicks(&domain_key, &code).await {
+                        tracing::error!("Failed to update click count: {}", e);
+                        metrics::BACKGROUND_TASK_ERRORS.with_label_values(&["click_update"]).inc();
+                    }
+                }
+                .instrument(tracing::info_span!("click_update", short_code)),
+            );
+        }
 
-        Ok(url.long_url)
+        self.record_visit(domain, short_code, visit, &redirect);
+        Ok(redirect)
     }
 
-    async fn get_url_stats(&self, short_code: &str) -> Result<UrlStatsResponse> {
+    #[instrument(skip(self))]
+    async fn preview_url(&self, domain: &str, short_code: &str) -> Result<TinyUrl> {
+        let now = Utc::now();
+        match self.repository.find_by_short_code(domain, short_code).await? {
+            // Links outside their window only show where their fallback goes
+            Some(url) if url.is_active(now) && (url.is_live(now) || url.fallback_url.is_some()) => Ok(url),
+            Some(url) if url.is_retired() => Err(AppError::Gone(format!(
+                "Short code '{}' was disabled or deleted",
+                short_code
+            ))),
+            _ => Err(AppError::NotFound(format!("Short code '{}' not found", short_code))),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_url_stats(&self, domain: &str, short_code: &str) -> Result<UrlStatsResponse> {
         let url = self
             .repository
-            .get_stats(short_code)
+            .get_stats(domain, short_code)
             .await?
             .ok_or_else(|| AppError::NotFound(format!("Short code '{}' not found", short_code)))?;
 
         // Get cached click count if available
-        let cache_clicks = if let Some(cached_clicks) = self.cache.get(&format!("clicks:{}", short_code)).await? {
+        let cache_clicks = if let Some(cached_clicks) = self.cache.get(&clicks_cache_key(domain, short_code)).await? {
             cached_clicks.parse::<i32>().unwrap_or(url.clicks)
         } else {
             url.clicks
         };
 
+        let unique_visitors = self.cache.count_unique(&uniques_cache_key(domain, short_code)).await?;
+        let today = Utc::now().date_naive();
+        let mut daily_unique_visitors = BTreeMap::new();
+        for days in 0..DAILY_UNIQUES_DAYS {
+            let day = today - chrono::Days::new(days);
+            let count = self
+                .cache
+                .count_unique(&daily_uniques_cache_key(domain, short_code, day))
+                .await?;
+            daily_unique_visitors.insert(day.format("%Y-%m-%d").to_string(), count);
+        }
+
+        let mut variant_clicks: HashMap<String, i64> = self
+            .repository
+            .variant_clicks(domain, short_code)
+            .await?
+            .into_iter()
+            .collect();
+        let variants = url
+            .variants
+            .0
+            .into_iter()
+            .map(|variant| VariantStats {
+                clicks: variant_clicks.remove(&variant.name).unwrap_or(0),
+                locked: url.locked_variant.as_deref() == Some(variant.name.as_str()),
+                name: variant.name,
+                url: variant.url,
+                weight: variant.weight,
+            })
+            .collect();
+
+        let bots: BTreeMap<String, i64> = self
+            .repository
+            .bot_clicks(domain, short_code)
+            .await?
+            .into_iter()
+            .collect();
+
         Ok(UrlStatsResponse {
             short_code: url.short_code,
             long_url: url.long_url,
             clicks: cache_clicks,
+            unique_visitors,
+            daily_unique_visitors,
             created_at: url.created_at,
             updated_at: url.updated_at,
+            title: url.title,
+            description: url.description,
+            tags: url.tags,
+            notes: url.notes,
+            page: url.page_metadata.map(|page| page.0),
+            countries: self
+                .repository
+                .country_clicks(domain, short_code)
+                .await?
+                .into_iter()
+                .collect(),
+            variants,
+            bot_clicks: bots.values().sum(),
+            bots,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_url_timeseries(
+        &self,
+        domain: &str,
+        short_code: &str,
+        interval: TimeseriesInterval,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<TimeseriesResponse> {
+        let url = self
+            .repository
+            .get_stats(domain, short_code)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Short code '{}' not found", short_code)))?;
+
+        let to = to.unwrap_or_else(Utc::now);
+        let from = interval.bucket_start(from.unwrap_or(to - interval.default_span()));
+        if from >= to {
+            return Err(AppError::Validation("from must be before to".to_string()));
+        }
+        if let (TimeseriesInterval::Hour, Some(retention)) = (interval, self.hourly_click_retention) {
+            // Pruning drops every hour that started before the cutoff
+            if from < Utc::now() - retention {
+                return Err(AppError::Validation(format!(
+                    "Hourly counts are kept for {} days; use a later from or interval=day",
+                    retention.num_days()
+                )));
+            }
+        }
+        let length = interval.length().num_seconds();
+        let buckets = ((to - from).num_seconds() + length - 1) / length;
+        if buckets > MAX_TIMESERIES_POINTS {
+            return Err(AppError::Validation(format!(
+                "A time series can have at most {} points; narrow the range or use a longer interval",
+                MAX_TIMESERIES_POINTS
+            )));
+        }
+
+        let mut points: Vec<TimeseriesPoint> = (0..buckets)
+            .map(|index| TimeseriesPoint {
+                start: from + chrono::Duration::seconds(index * length),
+                ..TimeseriesPoint::default()
+            })
+            .collect();
+        let rows = self
+            .repository
+            .click_rollups(domain, short_code, interval == TimeseriesInterval::Hour, from, to)
+            .await?;
+        for (bucket, dimension, value, clicks) in rows {
+            let index = (interval.bucket_start(bucket) - from).num_seconds() / length;
+            let Some(point) = usize::try_from(index).ok().and_then(|index| points.get_mut(index)) else {
+                continue;
+            };
+            let breakdown = match dimension.as_str() {
+                "total" => {
+                    point.clicks += clicks;
+                    continue;
+                }
+                "referrer" => &mut point.referrers,
+                "country" => &mut point.countries,
+                "device" => &mut point.devices,
+                "browser" => &mut point.browsers,
+                _ => continue,
+            };
+            *breakdown.entry(value).or_default() += clicks;
+        }
+
+        Ok(TimeseriesResponse {
+            short_code: url.short_code,
+            interval,
+            from,
+            to,
+            points,
         })
     }
 
-    async fn delete_url(&self, short_code: &str) -> Result<bool> {
-        // Delete from cache first
-        self.cache.delete(short_code).await?;
-        self.cache.delete(&format!("clicks:{}", short_code)).await?;
+    #[instrument(skip(self))]
+    async fn delete_url(&self, domain: &str, short_code: &str) -> Result<bool> {
+        // Delete from cache first; counters are kept in case the link is restored,
+        // and only go once its code is released
+        self.cache.delete(&url_cache_key(domain, short_code)).await?;
 
         // Delete from database
-        self.repository.delete_by_short_code(short_code).await
+        self.repository.delete_by_short_code(domain, short_code).await
     }
-} 
\ No newline at end of file
+
+    #[instrument(skip(self))]
+    async fn get_url(&self, domain: &str, short_code: &str) -> Result<TinyUrl> {
+        self.require_url(domain, short_code).await
+    }
+
+    #[instrument(skip(self, request))]
+    async fn update_url(
+        &self,
+        domain: &str,
+        short_code: &str,
+        request: UpdateUrlRequest,
+    ) -> Result<TinyUrl> {
+        request.validate()?;
+
+        let mut url = self.require_url(domain, short_code).await?;
+        let destination_changed = request.url.as_ref().is_some_and(|long_url| *long_url != url.long_url);
+        if let Some(long_url) = request.url {
+            url.long_url = long_url;
+        }
+        if request.expires_at.is_some() || request.clear_expires_at {
+            url.expires_at = request.expires_at;
+        }
+        if request.active_from.is_some() || request.clear_active_from {
+            url.active_from = request.active_from;
+        }
+        if request.active_until.is_some() || request.clear_active_until {
+            url.active_until = request.active_until;
+        }
+        if let Some(fallback_url) = request.fallback_url {
+            url.fallback_url = Some(fallback_url).filter(|fallback_url| !fallback_url.is_empty());
+        }
+        validate_schedule(url.active_from, url.active_until, url.fallback_url.as_deref())
+            .map_err(AppError::Validation)?;
+        if let Some(interstitial) = request.interstitial {
+            url.interstitial = interstitial;
+        }
+        if let Some(rules) = request.redirect_rules {
+            url.redirect_rules = Json(rules);
+        }
+        if let Some(locked) = request.locked_variant {
+            url.locked_variant = Some(locked).filter(|locked| !locked.is_empty());
+        }
+        let variants = request.variants.unwrap_or_else(|| url.variants.0.clone());
+        url.variants = Json(
+            normalize_variants(variants, url.locked_variant.as_deref()).map_err(AppError::Validation)?,
+        );
+        if let Some(utm) = request.utm {
+            url.utm = Json(utm.merged_into(&url.utm).map_err(AppError::Validation)?);
+        }
+        if let Some(passthrough) = request.passthrough {
+            url.passthrough = passthrough;
+        }
+        if let Some(template) = request.template {
+            url.template = template;
+        }
+        url.check_template().map_err(AppError::Validation)?;
+        let metadata = LinkMetadata::new(
+            request.title.as_deref().or(url.title.as_deref()),
+            request.description.as_deref().or(url.description.as_deref()),
+            request.tags.as_deref().unwrap_or(&url.tags),
+            request.notes.as_deref().or(url.notes.as_deref()),
+        )
+        .map_err(AppError::Validation)?;
+        url.set_metadata(metadata);
+        url.updated_at = Utc::now();
+
+        let updated = self.repository.update(&url).await?;
+        self.invalidate(domain, short_code).await?;
+        if destination_changed {
+            self.fetch_page_metadata(&updated);
+        }
+
+        Ok(updated)
+    }
+
+    #[instrument(skip(self))]
+    async fn set_disabled(
+        &self,
+        domain: &str,
+        short_code: &str,
+        reason: Option<String>,
+    ) -> Result<TinyUrl> {
+        let mut url = self.require_url(domain, short_code).await?;
+        url.disabled_reason = reason;
+        url.updated_at = Utc::now();
+
+        let updated = self.repository.update(&url).await?;
+        self.invalidate(domain, short_code).await?;
+
+        Ok(updated)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_links(&self, query: LinkListQuery) -> Result<LinkListResponse> {
+        let tags: Vec<&str> = query.tag.as_deref().unwrap_or_default().split(',').collect();
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_LIST_LIMIT)
+            .clamp(1, MAX_LIST_LIMIT);
+        let filter = LinkFilter {
+            domain: query.domain.as_deref().map(normalize_host),
+            tags: normalize_tags(&tags).map_err(AppError::Validation)?,
+            search: query
+                .q
+                .map(|q| q.trim().to_string())
+                .filter(|q| !q.is_empty()),
+            include_deleted: query.include_deleted,
+            before: query.before,
+            // One extra row tells whether another page follows
+            limit: limit + 1,
+        };
+
+        let mut links = self.repository.list_links(&filter).await?;
+        let next_before = if links.len() as i64 > limit {
+            links.truncate(limit as usize);
+            links.last().map(|url| url.id)
+        } else {
+            None
+        };
+
+        Ok(LinkListResponse { links, next_before })
+    }
+
+    #[instrument(skip(self))]
+    async fn restore_url(&self, domain: &str, short_code: &str) -> Result<TinyUrl> {
+        let mut url = self.require_url(domain, short_code).await?;
+        if !url.is_deleted() {
+            return Ok(url);
+        }
+
+        url.deleted_at = None;
+        url.updated_at = Utc::now();
+        self.repository.update(&url).await
+    }
+
+    #[instrument(skip(self))]
+    async fn roll_up_clicks(
+        &self,
+        events_before: DateTime<Utc>,
+        hours_before: DateTime<Utc>,
+    ) -> Result<RollupReport> {
+        let mut rolled_up = 0;
+        loop {
+            let batch = self.repository.roll_up_clicks(ROLLUP_BATCH_SIZE).await?;
+            if batch == 0 {
+                break;
+            }
+            rolled_up += batch;
+        }
+
+        Ok(RollupReport {
+            rolled_up,
+            pruned_events: self.repository.prune_click_events(events_before).await?,
+            pruned_hours: self.repository.prune_hourly_rollups(hours_before).await?,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn purge_expired(&self, before: DateTime<Utc>, dry_run: bool) -> Result<u64> {
+        if dry_run {
+            return self.repository.count_expired(before).await;
+        }
+
+        let deleted = self.repository.delete_expired(before).await?;
+        for (domain, short_code) in &deleted {
+            self.invalidate(domain, short_code).await?;
+        }
+
+        Ok(deleted.len() as u64)
+    }
+
+    #[instrument(skip(self))]
+    async fn rebuild_cache(&self) -> Result<CacheRebuildSummary> {
+        let mut summary = CacheRebuildSummary { cached: 0, evicted: 0 };
+        let mut after_id = 0;
+
+        loop {
+            let page = self.repository.list_page(after_id, SCAN_PAGE_SIZE).await?;
+            let Some(last) = page.last() else { break };
+            after_id = last.id;
+
+            let now = Utc::now();
+            for url in &page {
+                let cache_key = url_cache_key(&url.domain, &url.short_code);
+                if url.is_active(now) {
+                    self.cache
+                        .set(
+                            &cache_key,
+                            &Destination::of(url).to_cache_value(),
+                            self.cache_ttl_for(url, now),
+                        )
+                        .await?;
+                    summary.cached += 1;
+                } else {
+                    self.cache.delete(&cache_key).await?;
+                    summary.evicted += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    #[instrument(skip(self))]
+    async fn link_summary(&self) -> Result<LinkSummary> {
+        self.repository.summarize(Utc::now()).await
+    }
+
+    #[instrument(skip(self, input))]
+    async fn import_links(
+        &self,
+        input: Box<dyn Read + Send>,
+        options: ImportOptions,
+    ) -> Result<ImportReport> {
+        // The parser blocks on its input, so it runs off the runtime and hands
+        // validated batches to the repository as it goes
+        let (batches, received) = mpsc::channel(4);
+        let parser = tokio::task::spawn_blocking(move || parse_links(input, options.format, batches));
+        let outcome = self
+            .repository
+            .import_links(received, options.on_conflict, options.dry_run)
+            .await;
+        let parsed = parser
+            .await
+            .map_err(|e| AppError::Internal(format!("Import parser failed: {}", e)))?;
+
+        // A parse failure explains why the repository saw the input end early
+        let parsed = parsed?;
+        let outcome = outcome?;
+
+        for (domain, short_code) in &outcome.overwritten {
+            self.invalidate(domain, short_code).await?;
+        }
+
+        let mut issues = parsed.issues;
+        issues.extend(outcome.issues);
+        issues.sort_by_key(|issue| issue.line);
+        issues.truncate(MAX_IMPORT_ISSUES);
+
+        Ok(ImportReport {
+            dry_run: options.dry_run,
+            on_conflict: options.on_conflict,
+            rows_read: parsed.rows_read,
+            invalid: parsed.invalid + outcome.unknown_domains,
+            duplicates: outcome.duplicates,
+            conflicts: outcome.conflicts,
+            inserted: outcome.inserted,
+            updated: outcome.updated,
+            skipped: outcome.skipped,
+            failed: outcome.failed,
+            issues,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn export_links(&self, format: TransferFormat) -> Result<BoxStream<'static, Result<Bytes>>> {
+        self.repository.export_links(format).await
+    }
+
+    #[instrument(skip(self))]
+    async fn check_code_availability(
+        &self,
+        domain: Option<&str>,
+        code: &str,
+    ) -> Result<CodeAvailabilityResponse> {
+        let domain = self.registered_domain_key(domain).await?;
+        let code = self.generator.generate_custom(code)?;
+
+        let existing = self.repository.find_by_short_code(&domain, &code).await?;
+        if existing.is_none_or(|url| self.is_released(&url, Utc::now())) {
+            return Ok(CodeAvailabilityResponse {
+                code,
+                available: true,
+                suggestions: Vec::new(),
+            });
+        }
+
+        Ok(CodeAvailabilityResponse {
+            suggestions: self.suggest_free_codes(&domain, &code).await?,
+            code,
+            available: false,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_domain(&self, host: &str) -> Result<Option<Domain>> {
+        let host = normalize_host(host);
+        if host.is_empty() {
+            return Ok(None);
+        }
+
+        // Domains change rarely, so lookups are cached including misses
+        let cache_key = format!("domain:{}", host);
+        if let Some(cached) = self.cache.get(&cache_key).await? {
+            return Ok(serde_json::from_str(&cached).ok());
+        }
+
+        let domain = self.repository.find_domain(&host).await?;
+        let cached = match &domain {
+            Some(d) => serde_json::to_string(d)
+                .map_err(|e| AppError::Internal(format!("Failed to serialize domain: {}", e)))?,
+            None => String::new(),
+        };
+        self.cache.set(&cache_key, &cached, self.cache_ttl).await?;
+
+        Ok(domain)
+    }
+
+    #[instrument(skip_all, fields(host = %request.host))]
+    async fn create_domain(&self, request: CreateDomainRequest) -> Result<Domain> {
+        request.validate()?;
+
+        let host = normalize_host(&request.host);
+        if self.repository.find_domain(&host).await?.is_some() {
+            return Err(AppError::AlreadyExists(format!("Domain '{}' already exists", host)));
+        }
+
+        let mut domain = Domain::new(host, request.base_url);
+        domain.not_found_url = request.not_found_url;
+        domain.root_url = request.root_url;
+
+        let saved = self.repository.create_domain(&domain).await?;
+
+        // Drop any cached miss for this host
+        self.cache.delete(&format!("domain:{}", saved.host)).await?;
+
+        Ok(saved)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_domains(&self) -> Result<Vec<Domain>> {
+        self.repository.list_domains().await
+    }
+}
@@ -1,28 +1,151 @@
 use async_trait::async_trait;
 
-use crate::models::{TinyUrl, Result};
+use crate::models::{
+    ClickEvent, ConflictPolicy, Domain, ImportBatch, ImportOutcome, LinkFilter, LinkSummary, PageMetadata,
+    TinyUrl, Result, TransferFormat,
+};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
+use tokio::sync::mpsc;
 
 /// Repository trait for URL operations
+///
+/// Short codes are scoped by domain; the empty domain is the default one.
 #[async_trait]
 pub trait UrlRepository {
     /// Create a new URL entry
     async fn create(&self, url: &TinyUrl) -> Result<TinyUrl>;
     
     /// Find URL by short code
-    async fn find_by_short_code(&self, short_code: &str) -> Result<Option<TinyUrl>>;
+    async fn find_by_short_code(&self, domain: &str, short_code: &str) -> Result<Option<TinyUrl>>;
     
     /// Find URL by long URL
-    async fn find_by_long_url(&self, long_url: &str) -> Result<Option<TinyUrl>>;
+    async fn find_by_long_url(&self, domain: &str, long_url: &str) -> Result<Option<TinyUrl>>;
     
-    /// Update URL (mainly for click counting)
+    /// Update a link's destination, expiry and status
     async fn update(&self, url: &TinyUrl) -> Result<TinyUrl>;
+
+    /// Store fetched page metadata, unless the link's destination is no longer
+    /// `long_url`; false if nothing was updated
+    async fn set_page_metadata(
+        &self,
+        domain: &str,
+        short_code: &str,
+        long_url: &str,
+        metadata: &PageMetadata,
+    ) -> Result<bool>;
+
+    /// Atomically add one to a link's click count
+    async fn increment_clicks(&self, domain: &str, short_code: &str) -> Result<()>;
+
+    /// A link's clicks by variant name
+    async fn variant_clicks(&self, domain: &str, short_code: &str) -> Result<Vec<(String, i64)>>;
+
+    /// A link's clicks by visitor country, most first
+    async fn country_clicks(&self, domain: &str, short_code: &str) -> Result<Vec<(String, i64)>>;
+
+    /// Add one to a link's visits by `bot`, the pattern or heuristic that matched
+    async fn record_bot_click(&self, domain: &str, short_code: &str, bot: &str) -> Result<()>;
+
+    /// A link's bot visits by bot
+    async fn bot_clicks(&self, domain: &str, short_code: &str) -> Result<Vec<(String, i64)>>;
+
+    /// Store one human click for the hourly and daily rollups, and add it to the
+    /// link's clicks by country and by `variant` when known, in one statement
+    async fn record_click_event(
+        &self,
+        domain: &str,
+        short_code: &str,
+        event: &ClickEvent,
+        variant: Option<&str>,
+    ) -> Result<()>;
+
+    /// Add up to `limit` of the oldest clicks not rolled up yet to the hourly and
+    /// daily counts; returns how many, 0 when another process is rolling up
+    async fn roll_up_clicks(&self, limit: i64) -> Result<u64>;
+
+    /// Delete rolled-up clicks made before `before`
+    async fn prune_click_events(&self, before: DateTime<Utc>) -> Result<u64>;
+
+    /// Delete hourly counts of hours starting before `before`
+    async fn prune_hourly_rollups(&self, before: DateTime<Utc>) -> Result<u64>;
+
+    /// A link's rolled-up clicks starting in `from..to`, per hour or per day,
+    /// as (bucket start, dimension, value, clicks). The dimension is `total`
+    /// with an empty value, or `referrer`, `country`, `device` or `browser`.
+    async fn click_rollups(
+        &self,
+        domain: &str,
+        short_code: &str,
+        hourly: bool,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, String, String, i64)>>;
     
-    /// Delete URL by short code
-    async fn delete_by_short_code(&self, short_code: &str) -> Result<bool>;
+    /// Soft-delete a link, keeping its code reserved; false if there is no live link
+    async fn delete_by_short_code(&self, domain: &str, short_code: &str) -> Result<bool>;
+
+    /// Permanently remove a link deleted at or before `deleted_before`, freeing its code
+    async fn release_code(
+        &self,
+        domain: &str,
+        short_code: &str,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<bool>;
     
     /// Get URL statistics
-    async fn get_stats(&self, short_code: &str) -> Result<Option<TinyUrl>>;
+    async fn get_stats(&self, domain: &str, short_code: &str) -> Result<Option<TinyUrl>>;
     
     /// Check if short code exists
-    async fn exists(&self, short_code: &str) -> Result<bool>;
-} 
\ No newline at end of file
+    async fn exists(&self, domain: &str, short_code: &str) -> Result<bool>;
+
+    /// Those of `short_codes` that exist on `domain`
+    async fn existing_codes(&self, domain: &str, short_codes: &[String]) -> Result<Vec<String>>;
+
+    /// Up to `limit` links with an ID above `after_id`, in ID order, for full scans
+    async fn list_page(&self, after_id: i32, limit: i64) -> Result<Vec<TinyUrl>>;
+
+    /// Links matching `filter`, newest first
+    async fn list_links(&self, filter: &LinkFilter) -> Result<Vec<TinyUrl>>;
+
+    /// Count links that expired at or before `before` and are not deleted yet
+    async fn count_expired(&self, before: DateTime<Utc>) -> Result<u64>;
+
+    /// Soft-delete links that expired at or before `before`, so their codes stay
+    /// quarantined like any other deletion; returns their domain and code
+    async fn delete_expired(&self, before: DateTime<Utc>) -> Result<Vec<(String, String)>>;
+
+    /// Link and click totals as of `now`
+    async fn summarize(&self, now: DateTime<Utc>) -> Result<LinkSummary>;
+}
+
+/// Repository trait for custom domain operations
+#[async_trait]
+pub trait DomainRepository {
+    /// Register a new domain
+    async fn create_domain(&self, domain: &Domain) -> Result<Domain>;
+
+    /// Find a domain by its host name
+    async fn find_domain(&self, host: &str) -> Result<Option<Domain>>;
+
+    /// List all registered domains
+    async fn list_domains(&self) -> Result<Vec<Domain>>;
+}
+
+/// Repository trait for bulk link import and export
+#[async_trait]
+pub trait LinkTransferRepository {
+    /// Stage every batch until `ImportBatch::End`, then apply them in one
+    /// transaction; nothing is written if the input ends early, on a dry run,
+    /// or when `fail` finds an existing code
+    async fn import_links(
+        &self,
+        batches: mpsc::Receiver<ImportBatch>,
+        on_conflict: ConflictPolicy,
+        dry_run: bool,
+    ) -> Result<ImportOutcome>;
+
+    /// Stream all links, in ID order, in the given format
+    async fn export_links(&self, format: TransferFormat) -> Result<BoxStream<'static, Result<Bytes>>>;
+}
@@ -1,21 +1,128 @@
 use async_trait::async_trait;
 
-use crate::models::{CreateUrlRequest, CreateUrlResponse, UrlStatsResponse, Result};
+use crate::models::{
+    CacheRebuildSummary, CodeAvailabilityResponse, CreateDomainRequest, CreateUrlRequest,
+    CreateUrlResponse, Domain, ImportOptions, ImportReport, LinkListQuery, LinkListResponse,
+    LinkSummary, PageMetadata, Redirect, Result, RollupReport, TimeseriesInterval, TimeseriesResponse,
+    TinyUrl, TransferFormat, UpdateUrlRequest, UrlStatsResponse, VisitContext,
+};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
+use std::io::Read;
+use std::net::IpAddr;
 
 /// Service trait for URL shortening business logic
+///
+/// `domain` arguments are domain keys as stored on links, empty for the default domain.
 #[async_trait]
 pub trait UrlService {
-    /// Create a shortened URL
-    async fn create_short_url(&self, request: CreateUrlRequest) -> Result<CreateUrlResponse>;
+    /// Create a shortened URL; `base_url` is used unless the link's domain has its own
+    async fn create_short_url(
+        &self,
+        request: CreateUrlRequest,
+        base_url: &str,
+    ) -> Result<CreateUrlResponse>;
     
-    /// Get the original URL from short code
-    async fn get_original_url(&self, short_code: &str) -> Result<String>;
+    /// Resolve a short code for a redirect, counting a click; the visit
+    /// picks among the link's redirect rules
+    async fn get_original_url(
+        &self,
+        domain: &str,
+        short_code: &str,
+        visit: &VisitContext,
+    ) -> Result<Redirect>;
+
+    /// Get an active link for its preview page, without counting a click
+    async fn preview_url(&self, domain: &str, short_code: &str) -> Result<TinyUrl>;
     
     /// Get URL statistics
-    async fn get_url_stats(&self, short_code: &str) -> Result<UrlStatsResponse>;
+    async fn get_url_stats(&self, domain: &str, short_code: &str) -> Result<UrlStatsResponse>;
+
+    /// A link's rolled-up clicks from `from` (default: the interval's default
+    /// span before `to`) to `to` (default: now)
+    async fn get_url_timeseries(
+        &self,
+        domain: &str,
+        short_code: &str,
+        interval: TimeseriesInterval,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<TimeseriesResponse>;
     
-    /// Delete a shortened URL
-    async fn delete_url(&self, short_code: &str) -> Result<bool>;
+    /// Soft-delete a shortened URL; its code stays reserved for the quarantine period
+    async fn delete_url(&self, domain: &str, short_code: &str) -> Result<bool>;
+
+    /// Get a link with all its fields, whether or not it is active
+    async fn get_url(&self, domain: &str, short_code: &str) -> Result<TinyUrl>;
+
+    /// Change a link's destination or expiry
+    async fn update_url(
+        &self,
+        domain: &str,
+        short_code: &str,
+        request: UpdateUrlRequest,
+    ) -> Result<TinyUrl>;
+
+    /// Disable a link with a reason, or re-enable it with `None`
+    async fn set_disabled(
+        &self,
+        domain: &str,
+        short_code: &str,
+        reason: Option<String>,
+    ) -> Result<TinyUrl>;
+
+    /// One page of links matching the query, newest first
+    async fn list_links(&self, query: LinkListQuery) -> Result<LinkListResponse>;
+
+    /// Undo a deletion while the link's code is still reserved
+    async fn restore_url(&self, domain: &str, short_code: &str) -> Result<TinyUrl>;
+
+    /// Delete links expired at or before `before`, keeping their codes reserved for
+    /// the quarantine period, or only count them when `dry_run`
+    async fn purge_expired(&self, before: DateTime<Utc>, dry_run: bool) -> Result<u64>;
+
+    /// Roll up new clicks into hourly and daily counts, then delete raw clicks
+    /// made before `events_before` and hourly counts from before `hours_before`
+    async fn roll_up_clicks(
+        &self,
+        events_before: DateTime<Utc>,
+        hours_before: DateTime<Utc>,
+    ) -> Result<RollupReport>;
+
+    /// Rewrite cached destinations of all links from the database
+    async fn rebuild_cache(&self) -> Result<CacheRebuildSummary>;
+
+    /// Link and click totals
+    async fn link_summary(&self) -> Result<LinkSummary>;
+
+    /// Import links from CSV or JSONL, reporting what was done, or on a dry
+    /// run what would be
+    async fn import_links(
+        &self,
+        input: Box<dyn Read + Send>,
+        options: ImportOptions,
+    ) -> Result<ImportReport>;
+
+    /// Stream all links in the given format
+    async fn export_links(&self, format: TransferFormat) -> Result<BoxStream<'static, Result<Bytes>>>;
+
+    /// Check whether a custom code can be claimed on a registered domain
+    /// (the default one when `None`), suggesting free alternatives if not
+    async fn check_code_availability(
+        &self,
+        domain: Option<&str>,
+        code: &str,
+    ) -> Result<CodeAvailabilityResponse>;
+
+    /// Resolve a request host to a registered custom domain
+    async fn resolve_domain(&self, host: &str) -> Result<Option<Domain>>;
+
+    /// Register a custom domain
+    async fn create_domain(&self, request: CreateDomainRequest) -> Result<Domain>;
+
+    /// List registered custom domains
+    async fn list_domains(&self) -> Result<Vec<Domain>>;
 }
 
 /// Cache service trait for high-performance lookups
@@ -32,6 +139,32 @@ pub trait CacheService {
     
     /// Increment click counter
     async fn increment_clicks(&self, short_code: &str) -> Result<i64>;
+
+    /// Add a member to the approximate distinct count (HyperLogLog) under
+    /// `key`, which expires `ttl_seconds` after it is created when given
+    async fn add_unique(&self, key: &str, member: &str, ttl_seconds: Option<u64>) -> Result<()> {
+        self.add_uniques(&[(key, ttl_seconds)], member).await
+    }
+
+    /// Add a member to several distinct counts at once, given as `(key, ttl_seconds)`
+    /// pairs with the meaning of `add_unique`, in a single round trip
+    async fn add_uniques(&self, counts: &[(&str, Option<u64>)], member: &str) -> Result<()>;
+
+    /// Approximate number of distinct members added under `key`
+    async fn count_unique(&self, key: &str) -> Result<u64>;
+}
+
+/// Reads the title, Open Graph tags and favicon of link destinations
+#[async_trait]
+pub trait PageFetcher {
+    /// Fetch a page's metadata; failures are recorded in `PageMetadata::error`
+    async fn fetch(&self, url: &str) -> PageMetadata;
+}
+
+/// Finds the country of a visitor's IP address
+pub trait GeoLocator {
+    /// ISO 3166-1 alpha-2 code in upper case, `None` when the address is unknown
+    fn country(&self, ip: IpAddr) -> Option<String>;
 }
 
 /// URL shortening strategy trait
@@ -41,4 +174,12 @@ pub trait ShortCodeGenerator {
     
     /// Generate a custom short code
     fn generate_custom(&self, custom_code: &str) -> Result<String>;
+
+    /// Estimated entropy in bits of a generated code of the given length
+    fn entropy_bits(&self, length: usize) -> f64 {
+        length as f64 * 62f64.log2()
+    }
+
+    /// Propose close alternatives to a custom code, most similar first
+    fn suggest_alternatives(&self, custom_code: &str) -> Vec<String>;
 } 
\ No newline at end of file
@@ -0,0 +1,106 @@
+#!/usr/bin/env python3
+"""Write test-country.mmdb, a tiny IPv4 database in the MaxMind DB format.
+
+It is not a MaxMind database: it maps a few networks to made-up countries
+in the GeoIP2 Country layout (`country.iso_code`), for the GeoIP tests.
+
+    127.0.0.0/8   DE
+    10.0.0.0/8    US
+    192.0.2.0/24  FR
+
+Run from this directory: python3 make_test_country_mmdb.py
+"""
+import ipaddress
+
+NETWORKS = {"127.0.0.0/8": "DE", "10.0.0.0/8": "US", "192.0.2.0/24": "FR"}
+BUILD_EPOCH = 1767225600  # 2026-01-01, fixed so the file is reproducible
+
+
+def control(kind, size):
+    assert size < 29
+    if kind <= 7:
+        return bytes([(kind << 5) | size])
+    return bytes([size, kind - 7])
+
+
+def uint(kind, width, value):
+    data = value.to_bytes(width, "big").lstrip(b"\0")
+    return control(kind, len(data)) + data
+
+
+def string(value):
+    data = value.encode()
+    return control(2, len(data)) + data
+
+
+def uint16(value):
+    return uint(5, 2, value)
+
+
+def uint32(value):
+    return uint(6, 4, value)
+
+
+def uint64(value):
+    return uint(9, 8, value)
+
+
+def mapping(fields):
+    return control(7, len(fields)) + b"".join(string(k) + v for k, v in fields.items())
+
+
+def array(items):
+    return control(11, len(items)) + b"".join(items)
+
+
+def main():
+    data, offsets = b"", {}
+    for country in sorted(set(NETWORKS.values())):
+        offsets[country] = len(data)
+        data += mapping({"country": mapping({"iso_code": string(country)})})
+
+    # Binary search tree over the address bits; a record is a node index,
+    # the node count for "not found", or a data offset past the node count
+    nodes = [[None, None]]
+    for network, country in NETWORKS.items():
+        network = ipaddress.ip_network(network)
+        bits, node = int(network.network_address), 0
+        for i in range(network.prefixlen):
+            bit = (bits >> (31 - i)) & 1
+            if i == network.prefixlen - 1:
+                nodes[node][bit] = ("data", country)
+                break
+            if nodes[node][bit] is None:
+                nodes.append([None, None])
+                nodes[node][bit] = ("node", len(nodes) - 1)
+            node = nodes[node][bit][1]
+
+    count = len(nodes)
+    tree = b""
+    for records in nodes:
+        for record in records:
+            if record is None:
+                value = count
+            elif record[0] == "node":
+                value = record[1]
+            else:
+                value = count + 16 + offsets[record[1]]
+            tree += value.to_bytes(3, "big")
+
+    metadata = mapping({
+        "node_count": uint32(count),
+        "record_size": uint16(24),
+        "ip_version": uint16(4),
+        "database_type": string("Test-Country"),
+        "languages": array([string("en")]),
+        "binary_format_major_version": uint16(2),
+        "binary_format_minor_version": uint16(0),
+        "build_epoch": uint64(BUILD_EPOCH),
+        "description": mapping({"en": string("GeoIP test database")}),
+    })
+    with open("test-country.mmdb", "wb") as out:
+        out.write(tree + b"\0" * 16 + data + b"\xab\xcd\xefMaxMind.com" + metadata)
+
+
+if __name__ == "__main__":
+    main()